@@ -57,6 +57,7 @@ fn main() {
             "proto/world.proto",
             "proto/trajectory.proto",
             "proto/metrics.proto",
+            "proto/heatmap.proto",
         ],
         &["proto/"],
     )
@@ -66,8 +66,12 @@ impl Node for SslNetworkSimulator {
 
     fn new(input: Self::Input, output: Self::Output, config: Arc<Mutex<Config>>) -> Self {
         let mut trackers: HashMap<usize, SslSimulatorTrajectoryTracker> = HashMap::new();
+        let motion_config = config.lock().unwrap().motion.clone();
         for i in 0..=config.lock().unwrap().rules.max_robot_id {
-            trackers.insert(i as usize, SslSimulatorTrajectoryTracker::new(i as usize));
+            trackers.insert(
+                i as usize,
+                SslSimulatorTrajectoryTracker::new(i as usize, motion_config.clone()),
+            );
         }
 
         Self {
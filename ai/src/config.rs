@@ -3,6 +3,13 @@ use prost::Message;
 use protobuf;
 use std::error::Error;
 use std::fs;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// Path relative to Cargo.toml
+pub const CONFIG_FILEPATH: &str = "config/config.pbtxt";
+const CONFIG_RELOAD_PERIOD: Duration = Duration::from_secs(1);
 
 // Sadly the rust-protobuf crate always generates structs with Option<T>
 // fields, even if the field is "required" in proto2. There are accessor
@@ -10,11 +17,92 @@ use std::fs;
 // the interface of Prost, so we use rust-protobuf to read the text format
 // (since that is unsupported in Prost), and then convert to Prost
 pub fn load_config() -> Result<proto::config::Config, Box<dyn Error>> {
-    // Path relative to Cargo.toml
-    let config_filepath = "config/config.pbtxt";
-    let file_contents = fs::read_to_string(config_filepath)?;
-    let config = protobuf::text_format::parse_from_str(file_contents.as_str())?;
-    convert_rust_protobuf_to_prost(config)
+    let file_contents = fs::read_to_string(CONFIG_FILEPATH)?;
+    parse_config(&file_contents)
+}
+
+fn parse_config(file_contents: &str) -> Result<proto::config::Config, Box<dyn Error>> {
+    let config = protobuf::text_format::parse_from_str(file_contents)?;
+    let config = convert_rust_protobuf_to_prost(config)?;
+    validate_config(&config)?;
+    Ok(config)
+}
+
+// Values that parse fine but that the rest of the code can't work with
+fn validate_config(config: &proto::config::Config) -> Result<(), Box<dyn Error>> {
+    let spacing = config.gameplay.heatmap_grid_spacing;
+    if !(spacing > 0.0 && spacing.is_finite()) {
+        return Err(format!("heatmap_grid_spacing must be positive, not {}", spacing).into());
+    }
+    let command_mode = config.motion.command_mode;
+    if proto::config::RobotCommandMode::from_i32(command_mode).is_none() {
+        return Err(format!("Invalid command_mode {}", command_mode).into());
+    }
+    Ok(())
+}
+
+// Picks up edits to the config file while we're running, e.g. when the strategy is tuned
+// during a timeout. Only changes to the file are applied, so we don't undo changes made
+// through the GUI
+pub struct ConfigWatcher {
+    path: String,
+    last_contents: Option<String>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: &str) -> ConfigWatcher {
+        ConfigWatcher {
+            path: path.to_string(),
+            last_contents: fs::read_to_string(path).ok(),
+        }
+    }
+
+    // Checks the file on its own thread, so reading and parsing it never holds up a node.
+    // Each config that replaces the shared one is also sent on the returned channel, and the
+    // thread stops when that channel is dropped
+    pub fn spawn(
+        mut self,
+        config: Arc<Mutex<proto::config::Config>>,
+    ) -> mpsc::Receiver<Arc<proto::config::Config>> {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || loop {
+            thread::sleep(CONFIG_RELOAD_PERIOD);
+            if self.reload_if_changed(&config) {
+                let reloaded = Arc::new(config.lock().unwrap().clone());
+                if sender.send(reloaded).is_err() {
+                    return;
+                }
+            }
+        });
+        receiver
+    }
+
+    // Returns whether the config was replaced. A file that doesn't parse is reported once,
+    // and the config we already have is kept
+    pub fn reload_if_changed(&mut self, config: &Mutex<proto::config::Config>) -> bool {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(c) => c,
+            Err(_) => return false,
+        };
+        if self.last_contents.as_ref() == Some(&contents) {
+            return false;
+        }
+        self.last_contents = Some(contents.clone());
+        match parse_config(&contents) {
+            Ok(new_config) => {
+                *config.lock().unwrap() = new_config;
+                println!("Reloaded config from {}", self.path);
+                true
+            }
+            Err(e) => {
+                println!(
+                    "Warning: Keeping the previous config, {} is invalid: {}",
+                    self.path, e
+                );
+                false
+            }
+        }
+    }
 }
 
 fn convert_rust_protobuf_to_prost(
@@ -26,3 +114,83 @@ fn convert_rust_protobuf_to_prost(
         Err(e) => Err(Box::new(e)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn temp_config_file(name: &str, contents: &str) -> String {
+        let path = env::temp_dir().join(name).to_str().unwrap().to_string();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn changed_strategy_is_picked_up() {
+        let original = fs::read_to_string(CONFIG_FILEPATH).unwrap();
+        let path = temp_config_file("rustware_config_reload_test.pbtxt", &original);
+        let config = Mutex::new(load_config().unwrap());
+        let mut watcher = ConfigWatcher::new(&path);
+        assert!(!watcher.reload_if_changed(&config));
+
+        let changed = original.replace("aggressiveness: 0.5", "aggressiveness: 0.9");
+        fs::write(&path, changed).unwrap();
+        assert!(watcher.reload_if_changed(&config));
+        assert_eq!(config.lock().unwrap().gameplay.strategy.aggressiveness, 0.9);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn watcher_thread_sends_only_valid_reloads() {
+        let original = fs::read_to_string(CONFIG_FILEPATH).unwrap();
+        let path = temp_config_file("rustware_config_thread_test.pbtxt", &original);
+        let config = Arc::new(Mutex::new(load_config().unwrap()));
+        let updates = ConfigWatcher::new(&path).spawn(Arc::clone(&config));
+
+        let invalid = original.replace("aggressiveness: 0.5", "aggressiveness: very");
+        fs::write(&path, invalid).unwrap();
+        assert!(updates.recv_timeout(CONFIG_RELOAD_PERIOD * 3).is_err());
+
+        let changed = original.replace("aggressiveness: 0.5", "aggressiveness: 0.9");
+        fs::write(&path, changed).unwrap();
+        let reloaded = updates.recv_timeout(CONFIG_RELOAD_PERIOD * 3).unwrap();
+        assert_eq!(reloaded.gameplay.strategy.aggressiveness, 0.9);
+        assert_eq!(config.lock().unwrap().gameplay.strategy.aggressiveness, 0.9);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn non_positive_heatmap_spacing_is_rejected() {
+        let original = fs::read_to_string(CONFIG_FILEPATH).unwrap();
+        let path = temp_config_file("rustware_config_spacing_test.pbtxt", &original);
+        let config = Mutex::new(load_config().unwrap());
+        let mut watcher = ConfigWatcher::new(&path);
+
+        for spacing in ["0.0", "-0.2"] {
+            let invalid = original.replace(
+                "heatmap_grid_spacing: 0.2",
+                &format!("heatmap_grid_spacing: {}", spacing),
+            );
+            assert!(parse_config(&invalid).is_err());
+            fs::write(&path, invalid).unwrap();
+            assert!(!watcher.reload_if_changed(&config));
+            assert_eq!(config.lock().unwrap().gameplay.heatmap_grid_spacing, 0.2);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn invalid_config_keeps_previous_values() {
+        let original = fs::read_to_string(CONFIG_FILEPATH).unwrap();
+        let path = temp_config_file("rustware_config_invalid_test.pbtxt", &original);
+        let config = Mutex::new(load_config().unwrap());
+        let mut watcher = ConfigWatcher::new(&path);
+
+        let invalid = original.replace("aggressiveness: 0.5", "aggressiveness: very");
+        fs::write(&path, invalid).unwrap();
+        assert!(!watcher.reload_if_changed(&config));
+        assert_eq!(config.lock().unwrap().gameplay.strategy.aggressiveness, 0.5);
+        fs::remove_file(&path).unwrap();
+    }
+}
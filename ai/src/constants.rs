@@ -1,3 +1,4 @@
 pub const MILLIMETERS_PER_METER: f64 = 1000.0;
 pub const METERS_PER_MILLIMETER: f64 = 1.0 / MILLIMETERS_PER_METER;
 // static const METERS_PER_MILLIMETER =
+pub const GRAVITY: f64 = 9.81; // m/s^2
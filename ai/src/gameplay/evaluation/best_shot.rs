@@ -1,6 +1,24 @@
-use crate::geom::{Angle, Circle, Point};
+use crate::gameplay::world::{Robot, World};
+use crate::geom::{Angle, Circle, Point, Vector};
+use crate::math::sigmoid;
+use crate::proto::config::Rules;
 
-fn angle_sweep_circles(
+// Shots through gaps narrower than this are likely to hit someone, since kicks aren't
+// perfectly accurate and robots move
+const MIN_OPEN_ANGLE_DEG: f64 = 4.0;
+const OPEN_ANGLE_WIDTH_DEG: f64 = 6.0;
+// Far away shots give the goalie more time to react
+const MAX_SHOT_DIST: f64 = 6.0;
+const SHOT_DIST_WIDTH: f64 = 3.0;
+
+pub struct Shot {
+    // Where on the goal line to aim
+    pub target: Point,
+    pub open_angle: Angle,
+    pub score_probability: f64,
+}
+
+pub fn angle_sweep_circles(
     circles: Vec<Circle>,
     origin: Point,
     start: Point,
@@ -93,3 +111,244 @@ fn angle_sweep_circles(
         .collect();
     unshifted_open_angles
 }
+
+// The widest gap between the obstacles from the shooter to the goal. The ball can get
+// through wherever there's room for its center, so robots are inflated by the ball radius too
+fn best_shot(
+    world: &World,
+    shooter_position: &Point,
+    goal: Point,
+    obstacles: Vec<&Robot>,
+    rules: &Rules,
+) -> Option<Shot> {
+    // The sweep goes counterclockwise, which is from the right post to the left one
+    // as seen from the field
+    let goal_side = goal.x.signum();
+    let half_goal_width = Vector {
+        x: 0.0,
+        y: goal_side * world.field.goal_y_length / 2.0,
+    };
+    let obstacles: Vec<Circle> = obstacles
+        .into_iter()
+        .map(|r| Circle {
+            center: r.state.position,
+            radius: rules.robot_max_radius + rules.ball_max_radius,
+        })
+        .collect();
+    let (open_start, open_end) = angle_sweep_circles(
+        obstacles,
+        *shooter_position,
+        goal + (-half_goal_width),
+        goal + half_goal_width,
+    )
+    .into_iter()
+    .max_by_key(|(a, b)| (*b - *a).clamp2pi())?;
+
+    let open_angle = (open_end - open_start).clamp2pi();
+    let mid_angle = open_start + open_angle / 2.0;
+    let dist_to_goal_line = (goal.x - shooter_position.x) / mid_angle.cos();
+    let target = *shooter_position + Vector::from_angle(mid_angle, dist_to_goal_line);
+    let score_probability = sigmoid(
+        open_angle.degrees(),
+        MIN_OPEN_ANGLE_DEG,
+        OPEN_ANGLE_WIDTH_DEG,
+    ) * sigmoid(
+        -(target - shooter_position).length(),
+        -MAX_SHOT_DIST,
+        SHOT_DIST_WIDTH,
+    );
+    Some(Shot {
+        target,
+        open_angle,
+        score_probability,
+    })
+}
+
+// Every robot but the friendly shooter is in the way. There's no shooter when we're
+// only asking how good a spot is to shoot from
+pub fn best_shot_on_goal(
+    world: &World,
+    shooter_position: &Point,
+    shooter_id: Option<usize>,
+    rules: &Rules,
+) -> Option<Shot> {
+    let obstacles = world
+        .friendly_team
+        .all_robots()
+        .into_iter()
+        .filter(|r| Some(r.id) != shooter_id)
+        .chain(world.enemy_team.all_robots())
+        .collect();
+    best_shot(
+        world,
+        shooter_position,
+        world.field.enemy_goal_center(),
+        obstacles,
+        rules,
+    )
+}
+
+// For working out how to defend against a shot from the enemy, so the shooter is theirs
+pub fn best_shot_on_our_goal(
+    world: &World,
+    shooter_position: &Point,
+    shooter_id: Option<usize>,
+    rules: &Rules,
+) -> Option<Shot> {
+    let obstacles = world
+        .friendly_team
+        .all_robots()
+        .into_iter()
+        .chain(
+            world
+                .enemy_team
+                .all_robots()
+                .into_iter()
+                .filter(|r| Some(r.id) != shooter_id),
+        )
+        .collect();
+    best_shot(
+        world,
+        shooter_position,
+        world.field.friendly_goal_center(),
+        obstacles,
+        rules,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_config;
+    use crate::motion::KinematicState;
+    use crate::world::{Ball, BallFriction, Field, GameState, Team, TeamInfo};
+
+    fn robot_at(id: usize, position: Point) -> Robot {
+        Robot {
+            id,
+            state: KinematicState {
+                position,
+                orientation: Angle::zero(),
+                velocity: Vector::new(),
+                angular_velocity: Angle::zero(),
+            },
+        }
+    }
+
+    fn rules() -> Rules {
+        load_config().unwrap().rules
+    }
+
+    fn world_with_enemies(enemies: Vec<Robot>) -> World {
+        let team_info = TeamInfo {
+            name: String::new(),
+            is_blue: true,
+            score: 0,
+            goalie_id: 0,
+            defending_positive_side: false,
+        };
+        World {
+            field: Field::ssl_div_b(),
+            ball: Ball {
+                position: Point::new(),
+                velocity: Vector::new(),
+                friction: BallFriction {
+                    sliding_deceleration: 3.0,
+                    rolling_deceleration: 0.5,
+                    rolling_speed: 2.0,
+                },
+            },
+            friendly_team: Team::new(),
+            enemy_team: Team::new().with_robots(enemies).build(),
+            game_state: GameState::new(),
+            friendly_team_info: team_info.clone(),
+            enemy_team_info: team_info,
+        }
+    }
+
+    #[test]
+    fn open_goal_aims_at_center() {
+        let world = world_with_enemies(vec![]);
+        let shooter = Point { x: 2.5, y: 0.0 };
+        let shot = best_shot_on_goal(&world, &shooter, None, &rules()).unwrap();
+        assert!(shot.target.y.abs() < 1.0e-6);
+        assert!((shot.target.x - 4.5).abs() < 1.0e-6);
+        // The whole goal, 1 m wide seen from 2 m away
+        let expected = 2.0 * (0.5f64 / 2.0).atan();
+        assert!((shot.open_angle.radians() - expected).abs() < 1.0e-6);
+        assert!(shot.score_probability > 0.9);
+    }
+
+    #[test]
+    fn shot_goes_past_goalie() {
+        let goalie = robot_at(0, Point { x: 4.4, y: 0.1 });
+        let world = world_with_enemies(vec![goalie]);
+        let shooter = Point { x: 2.5, y: 0.0 };
+        let shot = best_shot_on_goal(&world, &shooter, None, &rules()).unwrap();
+        // The bigger gap is on the other side of the goalie
+        assert!(shot.target.y < -0.1);
+        let open_goal_angle = 2.0 * (0.5f64 / 2.0).atan();
+        assert!(shot.open_angle.radians() < open_goal_angle / 2.0);
+    }
+
+    #[test]
+    fn fully_blocked_shot() {
+        let wall = (0..5)
+            .map(|i| {
+                robot_at(
+                    i,
+                    Point {
+                        x: 3.0,
+                        y: -0.4 + 0.2 * i as f64,
+                    },
+                )
+            })
+            .collect();
+        let world = world_with_enemies(wall);
+        let shooter = Point { x: 2.5, y: 0.0 };
+        assert!(best_shot_on_goal(&world, &shooter, None, &rules()).is_none());
+    }
+
+    #[test]
+    fn shot_on_our_goal() {
+        let world = world_with_enemies(vec![]);
+        let shooter = Point { x: -2.5, y: 0.0 };
+        let shot = best_shot_on_our_goal(&world, &shooter, None, &rules()).unwrap();
+        assert!((shot.target.x + 4.5).abs() < 1.0e-6);
+        assert!(shot.target.y.abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn only_the_shooter_is_left_out() {
+        let shooter = Point { x: 2.5, y: 0.0 };
+        let mut world = world_with_enemies(vec![]);
+        world.friendly_team = Team::new()
+            .with_robots(vec![
+                robot_at(1, shooter),
+                // Right next to the shooter, in the way of the whole goal
+                robot_at(2, Point { x: 2.6, y: 0.0 }),
+            ])
+            .build();
+        assert!(best_shot_on_goal(&world, &shooter, Some(1), &rules()).is_none());
+
+        world.friendly_team = Team::new().with_robots(vec![robot_at(1, shooter)]).build();
+        assert!(best_shot_on_goal(&world, &shooter, Some(1), &rules()).is_some());
+        // The same id on the other team is still an obstacle
+        world.enemy_team = Team::new()
+            .with_robots(vec![robot_at(1, Point { x: 2.6, y: 0.0 })])
+            .build();
+        assert!(best_shot_on_goal(&world, &shooter, Some(1), &rules()).is_none());
+    }
+
+    #[test]
+    fn bigger_robots_leave_smaller_gaps() {
+        let goalie = robot_at(0, Point { x: 4.4, y: 0.1 });
+        let world = world_with_enemies(vec![goalie]);
+        let shooter = Point { x: 2.5, y: 0.0 };
+        let mut big_rules = rules();
+        big_rules.robot_max_radius *= 2.0;
+        let shot = best_shot_on_goal(&world, &shooter, None, &rules()).unwrap();
+        let big_shot = best_shot_on_goal(&world, &shooter, None, &big_rules).unwrap();
+        assert!(big_shot.open_angle < shot.open_angle);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::gameplay::evaluation::best_shot::best_shot_on_our_goal;
+use crate::gameplay::evaluation::opponent_model::OpponentStats;
+use crate::gameplay::evaluation::passing::{score_pass, Pass, DEFAULT_ENEMY_MOTION};
+use crate::gameplay::evaluation::threats::{EnemyRole, EnemyTracker};
+use crate::gameplay::world::{Robot, World};
+use crate::geom::{Angle, Point};
+use crate::proto::config::Rules;
+
+// Enemy passes are assumed to be kicked like this until we've seen how they kick
+const ENEMY_PASS_SPEED: f64 = 4.0;
+// Options less likely than this aren't worth preparing for
+const MIN_DESTINATION_SCORE: f64 = 0.05;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BallDestinationKind {
+    // A pass to the enemy robot with this id
+    Pass(usize),
+    Shot,
+}
+
+// Somewhere the enemy ball carrier might kick the ball
+#[derive(Clone, Debug)]
+pub struct BallDestination {
+    pub kind: BallDestinationKind,
+    pub target: Point,
+    // How likely the carrier is to choose this over the other options.
+    // The likelihoods of all the destinations add up to 1
+    pub likelihood: f64,
+}
+
+// The passing evaluation assumes we're the ones passing and attacking away from our goal,
+// so the enemy's passes are scored with everything rotated to their point of view
+fn rotate_point(p: &Point) -> Point {
+    Point { x: -p.x, y: -p.y }
+}
+
+fn rotate_robot(r: &Robot) -> Robot {
+    let mut rotated = r.clone();
+    rotated.state.position = rotate_point(&r.state.position);
+    rotated.state.velocity = -r.state.velocity;
+    rotated.state.orientation = r.state.orientation + Angle::half();
+    rotated
+}
+
+// Where the enemy ball carrier is likely to kick the ball, most likely first.
+// Empty if no enemy has the ball
+pub fn predict_ball_destinations(
+    world: &World,
+    tracker: &EnemyTracker,
+    opponent: &OpponentStats,
+    rules: &Rules,
+) -> Vec<BallDestination> {
+    let carrier_id = match tracker
+        .threats()
+        .iter()
+        .find(|t| t.role == EnemyRole::BallCarrier)
+    {
+        Some(t) => t.id,
+        None => return vec![],
+    };
+    let ball = world.ball.position;
+    let pass_speed = opponent.typical_kick_speed().unwrap_or(ENEMY_PASS_SPEED);
+
+    let receivers: Vec<Robot> = world
+        .enemy_team
+        .all_robots()
+        .into_iter()
+        .filter(|r| r.id != carrier_id)
+        .map(rotate_robot)
+        .collect();
+    let interceptors: Vec<Robot> = world
+        .friendly_team
+        .all_robots()
+        .into_iter()
+        .map(rotate_robot)
+        .collect();
+    let mut destinations: Vec<(BallDestination, f64)> = receivers
+        .iter()
+        .map(|r| {
+            let pass = Pass {
+                start: rotate_point(&ball),
+                end: r.state.position,
+                speed: pass_speed,
+                time_offset: 0.0,
+                friction: world.ball.friction,
+            };
+            let destination = BallDestination {
+                kind: BallDestinationKind::Pass(r.id),
+                target: rotate_point(&r.state.position),
+                likelihood: 0.0,
+            };
+            // Our robots are the ones intercepting, and we know how they move
+            let score = score_pass(
+                &pass,
+                &world.field,
+                &receivers,
+                &interceptors,
+                &DEFAULT_ENEMY_MOTION,
+                rules,
+            );
+            (destination, score)
+        })
+        .collect();
+    if let Some(shot) = best_shot_on_our_goal(world, &ball, Some(carrier_id), rules) {
+        destinations.push((
+            BallDestination {
+                kind: BallDestinationKind::Shot,
+                target: shot.target,
+                likelihood: 0.0,
+            },
+            shot.score_probability,
+        ));
+    }
+
+    destinations.retain(|(_, score)| *score > MIN_DESTINATION_SCORE);
+    let total: f64 = destinations.iter().map(|(_, score)| score).sum();
+    destinations.sort_by(|a, b| b.1.total_cmp(&a.1));
+    destinations
+        .into_iter()
+        .map(|(mut d, score)| {
+            d.likelihood = score / total;
+            d
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_config;
+    use crate::geom::Vector;
+    use crate::motion::KinematicState;
+    use crate::world::{Ball, BallFriction, Field, GameState, Team, TeamInfo};
+    use std::time::Instant;
+
+    fn robot(id: usize, position: Point) -> Robot {
+        Robot {
+            id,
+            state: KinematicState {
+                position,
+                orientation: Angle::zero(),
+                velocity: Vector::new(),
+                angular_velocity: Angle::zero(),
+            },
+        }
+    }
+
+    fn world(friendly: Vec<Robot>, enemy: Vec<Robot>, ball: Point) -> World {
+        let team_info = TeamInfo {
+            name: String::new(),
+            is_blue: true,
+            score: 0,
+            goalie_id: 0,
+            defending_positive_side: false,
+        };
+        World {
+            field: Field::ssl_div_b(),
+            ball: Ball {
+                position: ball,
+                velocity: Vector::new(),
+                friction: BallFriction {
+                    sliding_deceleration: 3.0,
+                    rolling_deceleration: 0.5,
+                    rolling_speed: 2.0,
+                },
+            },
+            friendly_team: Team::new().with_robots(friendly).build(),
+            enemy_team: Team::new().with_robots(enemy).build(),
+            game_state: GameState::new(),
+            friendly_team_info: team_info.clone(),
+            enemy_team_info: team_info,
+        }
+    }
+
+    #[test]
+    fn open_receiver_is_more_likely_than_covered_one() {
+        let ball = Point { x: 1.0, y: 0.0 };
+        let enemies = vec![
+            robot(1, Point { x: 1.15, y: 0.0 }),
+            robot(2, Point { x: -1.5, y: 2.0 }),
+            robot(3, Point { x: -1.5, y: -2.0 }),
+        ];
+        // Standing right in the lane to robot 3
+        let friendly = vec![robot(0, Point { x: -0.25, y: -1.0 })];
+        let world = world(friendly, enemies, ball);
+        let mut tracker = EnemyTracker::new();
+        let rules = load_config().unwrap().rules;
+        tracker.update(&world, &rules, Instant::now());
+
+        let destinations =
+            predict_ball_destinations(&world, &tracker, &OpponentStats::new(), &rules);
+        assert_eq!(destinations[0].kind, BallDestinationKind::Pass(2));
+        assert!(destinations
+            .iter()
+            .all(|d| d.kind != BallDestinationKind::Pass(1)));
+        let total: f64 = destinations.iter().map(|d| d.likelihood).sum();
+        assert!((total - 1.0).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn nothing_predicted_without_ball_carrier() {
+        let world = world(
+            vec![],
+            vec![robot(1, Point { x: 3.0, y: 0.0 })],
+            Point::new(),
+        );
+        let mut tracker = EnemyTracker::new();
+        let rules = load_config().unwrap().rules;
+        tracker.update(&world, &rules, Instant::now());
+        assert!(
+            predict_ball_destinations(&world, &tracker, &OpponentStats::new(), &rules).is_empty()
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use crate::communication::buffer::NodeSender;
+use crate::gameplay::evaluation::best_shot::best_shot_on_goal;
+use crate::gameplay::evaluation::passing::{
+    enemy_intercept_score, static_score, EnemyMotion, Pass, DEFAULT_ENEMY_MOTION,
+};
+use crate::gameplay::world::{Robot, World};
+use crate::geom::Point;
+use crate::math::sigmoid;
+use crate::proto::config::Rules;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter};
+
+// Passes to each point are assumed to be kicked like this
+const RECEIVE_PASS_SPEED: f64 = 4.0;
+const RECEIVE_PASS_TIME_OFFSET: f64 = 0.15;
+// Points closer than this to an enemy are under pressure from it
+const PRESSURE_RADIUS: f64 = 1.0;
+const PRESSURE_WIDTH: f64 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter, Display)]
+pub enum HeatmapKind {
+    // How good a pass from the ball to each point would be, if a friendly robot were there
+    PassReceive,
+    // How likely a shot on the enemy goal from each point is to score
+    ShotOpenness,
+    // How close each point is to enemy robots
+    EnemyPressure,
+}
+
+// A scalar field over the field, sampled on a regular grid
+#[derive(Clone, Debug)]
+pub struct Heatmap {
+    pub kind: HeatmapKind,
+    // The grid point with the smallest x and y
+    pub origin: Point,
+    pub spacing: f64,
+    pub num_x: usize,
+    pub num_y: usize,
+    // One row at a time, starting from the origin
+    pub values: Vec<f64>,
+}
+
+fn pass_receive_value(
+    world: &World,
+    enemies: &[Robot],
+    enemy_motion: &EnemyMotion,
+    rules: &Rules,
+    p: &Point,
+) -> f64 {
+    let pass = Pass {
+        start: world.ball.position,
+        end: *p,
+        speed: RECEIVE_PASS_SPEED,
+        time_offset: RECEIVE_PASS_TIME_OFFSET,
+        friction: world.ball.friction,
+    };
+    // The ball has to actually get there for the pass to be any good
+    if pass.time_to_complete().is_none() {
+        return 0.0;
+    }
+    static_score(p, &world.field) * enemy_intercept_score(&pass, enemies, enemy_motion, rules)
+}
+
+fn enemy_pressure_value(enemies: &[Robot], p: &Point) -> f64 {
+    // The chance that at least one enemy is close enough to get involved
+    1.0 - enemies
+        .iter()
+        .map(|r| {
+            let dist = (r.state.position - p).length();
+            1.0 - sigmoid(-dist, -PRESSURE_RADIUS, PRESSURE_WIDTH)
+        })
+        .product::<f64>()
+}
+
+impl Heatmap {
+    pub fn compute(
+        kind: HeatmapKind,
+        world: &World,
+        spacing: f64,
+        enemy_motion: &EnemyMotion,
+        rules: &Rules,
+    ) -> Heatmap {
+        let area = world.field.touch_lines();
+        let origin = area.bottom_left();
+        let num_x = (area.len_x() / spacing).floor() as usize + 1;
+        let num_y = (area.len_y() / spacing).floor() as usize + 1;
+        let enemies: Vec<Robot> = world.enemy_team.all_robots().into_iter().cloned().collect();
+        let mut heatmap = Heatmap {
+            kind,
+            origin,
+            spacing,
+            num_x,
+            num_y,
+            values: Vec::with_capacity(num_x * num_y),
+        };
+        for j in 0..num_y {
+            for i in 0..num_x {
+                let p = heatmap.point(i, j);
+                let value = match kind {
+                    HeatmapKind::PassReceive => {
+                        pass_receive_value(world, &enemies, enemy_motion, rules, &p)
+                    }
+                    HeatmapKind::ShotOpenness => best_shot_on_goal(world, &p, None, rules)
+                        .map_or(0.0, |s| s.score_probability),
+                    HeatmapKind::EnemyPressure => enemy_pressure_value(&enemies, &p),
+                };
+                heatmap.values.push(value);
+            }
+        }
+        heatmap
+    }
+
+    pub fn point(&self, i: usize, j: usize) -> Point {
+        Point {
+            x: self.origin.x + i as f64 * self.spacing,
+            y: self.origin.y + j as f64 * self.spacing,
+        }
+    }
+
+    // The value at the grid point closest to p
+    pub fn value_at(&self, p: &Point) -> f64 {
+        let index =
+            |v: f64, n: usize| (v / self.spacing).round().clamp(0.0, (n - 1) as f64) as usize;
+        let i = index(p.x - self.origin.x, self.num_x);
+        let j = index(p.y - self.origin.y, self.num_y);
+        self.values[j * self.num_x + i]
+    }
+
+    // The grid point with the highest value that's acceptable to the filter
+    pub fn best_point(&self, filter: impl Fn(&Point) -> bool) -> Option<(Point, f64)> {
+        self.values
+            .iter()
+            .enumerate()
+            .map(|(k, v)| (self.point(k % self.num_x, k / self.num_x), *v))
+            .filter(|(p, _)| filter(p))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+}
+
+// Heatmaps take a while to compute, so each one is only computed the first time
+// it's needed in a tick and then shared
+pub struct HeatmapCache {
+    spacing: f64,
+    enemy_motion: EnemyMotion,
+    rules: Rules,
+    heatmaps: RefCell<HashMap<HeatmapKind, Arc<Heatmap>>>,
+}
+
+impl HeatmapCache {
+    pub fn new(spacing: f64, rules: &Rules) -> HeatmapCache {
+        HeatmapCache {
+            spacing,
+            enemy_motion: DEFAULT_ENEMY_MOTION,
+            rules: rules.clone(),
+            heatmaps: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // Throws away last tick's heatmaps, since the world has changed
+    pub fn clear(&mut self, spacing: f64, enemy_motion: EnemyMotion, rules: &Rules) {
+        self.spacing = spacing;
+        self.enemy_motion = enemy_motion;
+        self.rules = rules.clone();
+        self.heatmaps.get_mut().clear();
+    }
+
+    pub fn get(&self, kind: HeatmapKind, world: &World) -> Arc<Heatmap> {
+        self.heatmaps
+            .borrow_mut()
+            .entry(kind)
+            .or_insert_with(|| {
+                Arc::new(Heatmap::compute(
+                    kind,
+                    world,
+                    self.spacing,
+                    &self.enemy_motion,
+                    &self.rules,
+                ))
+            })
+            .clone()
+    }
+
+    // Everything computed so far this tick
+    pub fn computed(&self) -> Vec<Arc<Heatmap>> {
+        self.heatmaps.borrow().values().cloned().collect()
+    }
+}
+
+// Computing every heatmap for the GUI is too slow to do between ticks, so it's done on its
+// own thread from a copy of the world. The thread stops when the publisher is dropped
+pub struct HeatmapPublisher {
+    snapshots: mpsc::SyncSender<(World, f64, EnemyMotion, Rules)>,
+}
+
+impl HeatmapPublisher {
+    pub fn new(mut output: NodeSender<Vec<Arc<Heatmap>>>) -> HeatmapPublisher {
+        let (snapshots, receiver) = mpsc::sync_channel::<(World, f64, EnemyMotion, Rules)>(1);
+        thread::spawn(move || {
+            for (world, spacing, enemy_motion, rules) in receiver {
+                let heatmaps = HeatmapKind::iter()
+                    .map(|k| Arc::new(Heatmap::compute(k, &world, spacing, &enemy_motion, &rules)))
+                    .collect();
+                let _ = output.try_send(heatmaps);
+            }
+        });
+        HeatmapPublisher { snapshots }
+    }
+
+    // Computed the same way as the cache's heatmaps. Never waits, so if the last snapshot
+    // is still being worked on this one is dropped
+    pub fn publish(&self, world: &World, cache: &HeatmapCache) {
+        let _ = self.snapshots.try_send((
+            world.clone(),
+            cache.spacing,
+            cache.enemy_motion,
+            cache.rules.clone(),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::communication::buffer::node_connection;
+    use crate::config::load_config;
+    use crate::geom::{Angle, Vector};
+    use crate::motion::KinematicState;
+    use crate::world::{Ball, BallFriction, Field, GameState, Team, TeamInfo};
+
+    fn world_with_enemies(enemies: &[Point]) -> World {
+        let robots = enemies
+            .iter()
+            .enumerate()
+            .map(|(id, p)| Robot {
+                id,
+                state: KinematicState {
+                    position: *p,
+                    orientation: Angle::zero(),
+                    velocity: Vector::new(),
+                    angular_velocity: Angle::zero(),
+                },
+            })
+            .collect();
+        let team_info = TeamInfo {
+            name: String::new(),
+            is_blue: true,
+            score: 0,
+            goalie_id: 0,
+            defending_positive_side: false,
+        };
+        World {
+            field: Field::ssl_div_b(),
+            ball: Ball {
+                position: Point::new(),
+                velocity: Vector::new(),
+                friction: BallFriction {
+                    sliding_deceleration: 3.0,
+                    rolling_deceleration: 0.5,
+                    rolling_speed: 2.0,
+                },
+            },
+            friendly_team: Team::new(),
+            enemy_team: Team::new().with_robots(robots).build(),
+            game_state: GameState::new(),
+            friendly_team_info: team_info.clone(),
+            enemy_team_info: team_info,
+        }
+    }
+
+    #[test]
+    fn pressure_is_highest_near_enemies() {
+        let enemy = Point { x: 1.0, y: 1.0 };
+        let world = world_with_enemies(&[enemy]);
+        let heatmap = Heatmap::compute(
+            HeatmapKind::EnemyPressure,
+            &world,
+            0.25,
+            &DEFAULT_ENEMY_MOTION,
+            &load_config().unwrap().rules,
+        );
+        let (best, _) = heatmap.best_point(|_| true).unwrap();
+        assert!((best - enemy).length() < 0.25);
+        assert!(heatmap.value_at(&Point { x: -3.0, y: -2.0 }) < 0.01);
+    }
+
+    #[test]
+    fn publisher_sends_every_heatmap() {
+        let (metrics_sender, _metrics_receiver) = multiqueue2::broadcast_queue::<(String, f64)>(10);
+        let (sender, receiver) =
+            node_connection::<Vec<Arc<Heatmap>>>(1, metrics_sender, "Heatmaps".to_string());
+        let publisher = HeatmapPublisher::new(sender);
+        let cache = HeatmapCache::new(0.5, &load_config().unwrap().rules);
+        publisher.publish(&world_with_enemies(&[]), &cache);
+        let heatmaps = receiver.recv().unwrap();
+        let kinds: Vec<HeatmapKind> = heatmaps.iter().map(|h| h.kind).collect();
+        assert_eq!(kinds, HeatmapKind::iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn cache_computes_each_heatmap_once_per_tick() {
+        let world = world_with_enemies(&[]);
+        let rules = load_config().unwrap().rules;
+        let mut cache = HeatmapCache::new(0.5, &rules);
+        let first = cache.get(HeatmapKind::ShotOpenness, &world);
+        let second = cache.get(HeatmapKind::ShotOpenness, &world);
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(cache.computed().len(), 1);
+        cache.clear(0.5, DEFAULT_ENEMY_MOTION, &rules);
+        assert!(cache.computed().is_empty());
+    }
+}
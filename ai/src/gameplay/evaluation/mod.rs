@@ -1,2 +1,8 @@
 pub mod best_shot;
+pub mod enemy_prediction;
+pub mod heatmap;
+pub mod opponent_model;
+pub mod pass_optimizer;
 pub mod passing;
+pub mod possession;
+pub mod threats;
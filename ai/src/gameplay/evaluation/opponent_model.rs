@@ -0,0 +1,614 @@
+use crate::gameplay::evaluation::passing::{EnemyMotion, DEFAULT_ENEMY_MOTION};
+use crate::gameplay::evaluation::threats::EnemyTracker;
+use crate::gameplay::world::{Field, Robot, World};
+use crate::geom::{Point, Rectangle, Vector};
+use crate::motion::dribble::has_ball;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// A jump in ball speed this big with a robot next to the ball is a kick
+const KICK_SPEED_INCREASE: f64 = 1.0;
+const KICKER_MAX_DIST: f64 = 0.3;
+// An enemy that changes velocity this much after we kick has reacted to it
+const REACTION_VELOCITY_CHANGE: f64 = 0.5;
+// Anything that happens later than this isn't a reaction to the kick
+const MAX_REACTION_DELAY: f64 = 1.0;
+// Kickoffs within this angle of straight at our goal go forward
+const FORWARD_KICKOFF_MAX_ANGLE_DEG: f64 = 30.0;
+// Where passes end up is counted in a coarse grid over the field
+const NUM_PASS_AREAS_X: usize = 3;
+const NUM_PASS_AREAS_Y: usize = 3;
+// Statistics with fewer samples than this aren't trusted over our assumptions
+const MIN_SAMPLES: usize = 5;
+// Robots have to be watched for a while before the speed we've seen is close to their top speed
+const MIN_MOTION_SAMPLES: usize = 300;
+const SAVE_PERIOD: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KickoffDirection {
+    PositiveY,
+    Forward,
+    NegativeY,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Average {
+    total: f64,
+    count: usize,
+}
+
+impl Average {
+    fn new() -> Average {
+        Average {
+            total: 0.0,
+            count: 0,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.total += value;
+        self.count += 1;
+    }
+
+    fn mean(&self) -> Option<f64> {
+        if self.count >= MIN_SAMPLES {
+            Some(self.total / self.count as f64)
+        } else {
+            None
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({"total": self.total, "count": self.count})
+    }
+
+    fn from_json(value: &Value) -> Option<Average> {
+        Some(Average {
+            total: value["total"].as_f64()?,
+            count: value["count"].as_u64()? as usize,
+        })
+    }
+}
+
+// What we've learned about one opponent, over this and previous matches
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpponentStats {
+    kick_speed: Average,
+    reaction_delay: Average,
+    // How many of their players stay behind the ball while we have it
+    num_defenders: Average,
+    // How many of their passes were received in each area, one row at a time
+    // starting from the smallest x and y
+    pass_area_counts: Vec<usize>,
+    // Indexed in the order of KickoffDirection
+    kickoff_counts: Vec<usize>,
+    max_speed: Option<f64>,
+    max_acceleration: Option<f64>,
+}
+
+fn kickoff_index(direction: KickoffDirection) -> usize {
+    match direction {
+        KickoffDirection::PositiveY => 0,
+        KickoffDirection::Forward => 1,
+        KickoffDirection::NegativeY => 2,
+    }
+}
+
+fn pass_area_rect(field: &Field, index: usize) -> Rectangle {
+    let area = field.touch_lines();
+    let size_x = area.len_x() / NUM_PASS_AREAS_X as f64;
+    let size_y = area.len_y() / NUM_PASS_AREAS_Y as f64;
+    let corner = area.bottom_left()
+        + Vector {
+            x: (index % NUM_PASS_AREAS_X) as f64 * size_x,
+            y: (index / NUM_PASS_AREAS_X) as f64 * size_y,
+        };
+    Rectangle::new(
+        corner,
+        corner
+            + Vector {
+                x: size_x,
+                y: size_y,
+            },
+    )
+}
+
+fn pass_area_index(field: &Field, p: &Point) -> usize {
+    let area = field.touch_lines();
+    let p = area.clamp(p) - area.bottom_left();
+    let index = |v: f64, len: f64, n: usize| ((v / len * n as f64) as usize).min(n - 1);
+    let i = index(p.x, area.len_x(), NUM_PASS_AREAS_X);
+    let j = index(p.y, area.len_y(), NUM_PASS_AREAS_Y);
+    j * NUM_PASS_AREAS_X + i
+}
+
+fn optional_to_json(value: Option<f64>) -> Value {
+    match value {
+        Some(v) => json!(v),
+        None => Value::Null,
+    }
+}
+
+impl OpponentStats {
+    pub fn new() -> OpponentStats {
+        OpponentStats {
+            kick_speed: Average::new(),
+            reaction_delay: Average::new(),
+            num_defenders: Average::new(),
+            pass_area_counts: vec![0; NUM_PASS_AREAS_X * NUM_PASS_AREAS_Y],
+            kickoff_counts: vec![0; 3],
+            max_speed: None,
+            max_acceleration: None,
+        }
+    }
+
+    pub fn typical_kick_speed(&self) -> Option<f64> {
+        self.kick_speed.mean()
+    }
+
+    pub fn typical_num_defenders(&self) -> Option<f64> {
+        self.num_defenders.mean()
+    }
+
+    // The area of the field they pass to most, if they've passed often enough to tell
+    pub fn preferred_pass_area(&self, field: &Field) -> Option<Rectangle> {
+        if self.pass_area_counts.iter().sum::<usize>() < MIN_SAMPLES {
+            return None;
+        }
+        let (index, _) = self
+            .pass_area_counts
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, c)| **c)?;
+        Some(pass_area_rect(field, index))
+    }
+
+    pub fn usual_kickoff_direction(&self) -> Option<KickoffDirection> {
+        if self.kickoff_counts.iter().sum::<usize>() < MIN_SAMPLES {
+            return None;
+        }
+        [
+            KickoffDirection::PositiveY,
+            KickoffDirection::Forward,
+            KickoffDirection::NegativeY,
+        ]
+        .into_iter()
+        .max_by_key(|d| self.kickoff_counts[kickoff_index(*d)])
+    }
+
+    // How their robots move when trying to intercept our passes, falling back to our
+    // assumptions for anything we haven't learned yet
+    pub fn enemy_motion(&self) -> EnemyMotion {
+        EnemyMotion {
+            max_speed: self.max_speed.unwrap_or(DEFAULT_ENEMY_MOTION.max_speed),
+            max_acceleration: self
+                .max_acceleration
+                .unwrap_or(DEFAULT_ENEMY_MOTION.max_acceleration),
+            reaction_delay: self
+                .reaction_delay
+                .mean()
+                .unwrap_or(DEFAULT_ENEMY_MOTION.reaction_delay),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "kick_speed": self.kick_speed.to_json(),
+            "reaction_delay": self.reaction_delay.to_json(),
+            "num_defenders": self.num_defenders.to_json(),
+            "pass_area_counts": self.pass_area_counts,
+            "kickoff_counts": self.kickoff_counts,
+            "max_speed": optional_to_json(self.max_speed),
+            "max_acceleration": optional_to_json(self.max_acceleration),
+        })
+    }
+
+    fn from_json(value: &Value) -> Option<OpponentStats> {
+        let counts = |key: &str, len: usize| -> Option<Vec<usize>> {
+            let counts: Vec<usize> = value[key]
+                .as_array()?
+                .iter()
+                .map(|c| c.as_u64().map(|c| c as usize))
+                .collect::<Option<Vec<usize>>>()?;
+            if counts.len() == len {
+                Some(counts)
+            } else {
+                None
+            }
+        };
+        Some(OpponentStats {
+            kick_speed: Average::from_json(&value["kick_speed"])?,
+            reaction_delay: Average::from_json(&value["reaction_delay"])?,
+            num_defenders: Average::from_json(&value["num_defenders"])?,
+            pass_area_counts: counts("pass_area_counts", NUM_PASS_AREAS_X * NUM_PASS_AREAS_Y)?,
+            kickoff_counts: counts("kickoff_counts", 3)?,
+            max_speed: value["max_speed"].as_f64(),
+            max_acceleration: value["max_acceleration"].as_f64(),
+        })
+    }
+}
+
+enum FileRequest {
+    Save { path: PathBuf, data: String },
+    Load { path: PathBuf, team_name: String },
+}
+
+fn write_stats(path: &Path, data: &str) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, data)?;
+    Ok(())
+}
+
+fn read_stats(path: &Path) -> Option<OpponentStats> {
+    let data = fs::read_to_string(path).ok()?;
+    OpponentStats::from_json(&serde_json::from_str::<Value>(&data).ok()?)
+}
+
+// Reading and writing the team files can block for a while, so it's done on its own thread.
+// The thread stops when the model is dropped
+fn spawn_file_thread() -> (
+    mpsc::Sender<FileRequest>,
+    mpsc::Receiver<(String, OpponentStats)>,
+) {
+    let (requests, request_receiver) = mpsc::channel::<FileRequest>();
+    let (loaded_sender, loaded) = mpsc::channel();
+    thread::spawn(move || {
+        // So a broken directory doesn't print every time we try to save
+        let mut save_failed = false;
+        for request in request_receiver {
+            match request {
+                FileRequest::Save { path, data } => match write_stats(&path, &data) {
+                    Ok(()) => save_failed = false,
+                    Err(e) => {
+                        if !save_failed {
+                            println!("Couldn't save opponent model to {}: {}", path.display(), e);
+                        }
+                        save_failed = true;
+                    }
+                },
+                FileRequest::Load { path, team_name } => {
+                    let stats = read_stats(&path).unwrap_or_else(OpponentStats::new);
+                    let _ = loaded_sender.send((team_name, stats));
+                }
+            }
+        }
+    });
+    (requests, loaded)
+}
+
+struct Kick {
+    is_enemy: bool,
+    robot_id: usize,
+    velocity: Vector,
+}
+
+// Learns about the opponent as the match goes on, and remembers it for next time
+pub struct OpponentModel {
+    team_name: String,
+    stats: OpponentStats,
+    last_save_time: Instant,
+    files: mpsc::Sender<FileRequest>,
+    loaded: mpsc::Receiver<(String, OpponentStats)>,
+    // Until the last time we played them has been read, there's nothing worth saving
+    loading: bool,
+    previous_ball_velocity: Vector,
+    // Set when they kick, until someone gets the ball
+    enemy_passer: Option<usize>,
+    their_kickoff_pending: bool,
+    // When we kicked, and how each enemy was moving then
+    reaction_start: Option<(Instant, HashMap<usize, Vector>)>,
+}
+
+fn team_file(dir: &str, team_name: &str) -> PathBuf {
+    let file_name: String = team_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    PathBuf::from(dir).join(format!("{}.json", file_name))
+}
+
+fn detect_kick(world: &World, previous_ball_velocity: &Vector) -> Option<Kick> {
+    let ball = &world.ball;
+    if ball.velocity.length() - previous_ball_velocity.length() < KICK_SPEED_INCREASE {
+        return None;
+    }
+    let closest = |robots: Vec<&Robot>| {
+        robots
+            .into_iter()
+            .map(|r| (r.id, (r.state.position - ball.position).length()))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    };
+    let friendly = closest(world.friendly_team.all_robots());
+    let enemy = closest(world.enemy_team.all_robots());
+    let (is_enemy, (robot_id, dist)) = match (friendly, enemy) {
+        (Some(f), Some(e)) if e.1 < f.1 => (true, e),
+        (Some(f), _) => (false, f),
+        (None, Some(e)) => (true, e),
+        (None, None) => return None,
+    };
+    if dist > KICKER_MAX_DIST {
+        return None;
+    }
+    Some(Kick {
+        is_enemy,
+        robot_id,
+        velocity: ball.velocity,
+    })
+}
+
+fn kickoff_direction(velocity: &Vector, field: &Field) -> KickoffDirection {
+    // They attack towards our goal
+    let toward_our_goal = Vector {
+        x: field.friendly_side(),
+        y: 0.0,
+    };
+    let angle = (velocity.orientation() - toward_our_goal.orientation()).clamp_pos_neg_pi();
+    if angle.degrees().abs() < FORWARD_KICKOFF_MAX_ANGLE_DEG {
+        KickoffDirection::Forward
+    } else if velocity.y > 0.0 {
+        KickoffDirection::PositiveY
+    } else {
+        KickoffDirection::NegativeY
+    }
+}
+
+impl OpponentModel {
+    pub fn new() -> OpponentModel {
+        let (files, loaded) = spawn_file_thread();
+        OpponentModel {
+            team_name: String::new(),
+            stats: OpponentStats::new(),
+            last_save_time: Instant::now(),
+            files,
+            loaded,
+            loading: false,
+            previous_ball_velocity: Vector::new(),
+            enemy_passer: None,
+            their_kickoff_pending: false,
+            reaction_start: None,
+        }
+    }
+
+    pub fn stats(&self) -> &OpponentStats {
+        &self.stats
+    }
+
+    // Saves what we've learned to the directory, in a file named after the team. The file
+    // is written in the background
+    pub fn save(&self, dir: &str) {
+        if self.team_name.is_empty() || self.loading {
+            return;
+        }
+        match serde_json::to_string_pretty(&self.stats.to_json()) {
+            Ok(data) => {
+                let _ = self.files.send(FileRequest::Save {
+                    path: team_file(dir, &self.team_name),
+                    data,
+                });
+            }
+            Err(e) => println!("Couldn't save opponent model for {}: {}", self.team_name, e),
+        }
+    }
+
+    // Starts learning about a new opponent, picking up from the last time we played them
+    // once their file has been read
+    fn switch_team(&mut self, team_name: &str, dir: &str) {
+        self.save(dir);
+        self.team_name = team_name.to_string();
+        self.stats = OpponentStats::new();
+        self.loading = true;
+        let _ = self.files.send(FileRequest::Load {
+            path: team_file(dir, team_name),
+            team_name: team_name.to_string(),
+        });
+    }
+
+    // What we learn while the file is being read is thrown away, but that's only a few ticks
+    fn receive_loaded_stats(&mut self) {
+        while let Ok((team_name, stats)) = self.loaded.try_recv() {
+            if team_name == self.team_name {
+                self.stats = stats;
+                self.loading = false;
+            }
+        }
+    }
+
+    pub fn update(&mut self, world: &World, tracker: &EnemyTracker, dir: &str, time: Instant) {
+        let team_name = world.enemy_team_info.name.as_str();
+        if team_name != self.team_name {
+            self.switch_team(team_name, dir);
+        }
+        self.receive_loaded_stats();
+
+        if tracker.num_motion_samples() >= MIN_MOTION_SAMPLES {
+            let max = |known: Option<f64>, seen: f64| Some(known.map_or(seen, |k| k.max(seen)));
+            self.stats.max_speed = max(self.stats.max_speed, tracker.max_speed());
+            self.stats.max_acceleration =
+                max(self.stats.max_acceleration, tracker.max_acceleration());
+        }
+
+        if world.game_state.their_kickoff() {
+            self.their_kickoff_pending = true;
+        }
+        if let Some(kick) = detect_kick(world, &self.previous_ball_velocity) {
+            self.record_kick(world, &kick, time);
+        }
+        self.update_pass_receiver(world);
+        self.update_reaction(world, time);
+        self.update_formation(world);
+        self.previous_ball_velocity = world.ball.velocity;
+
+        if time.saturating_duration_since(self.last_save_time) > SAVE_PERIOD {
+            self.save(dir);
+            self.last_save_time = time;
+        }
+    }
+
+    fn record_kick(&mut self, world: &World, kick: &Kick, time: Instant) {
+        if kick.is_enemy {
+            self.stats.kick_speed.add(kick.velocity.length());
+            if self.their_kickoff_pending {
+                let index = kickoff_index(kickoff_direction(&kick.velocity, &world.field));
+                self.stats.kickoff_counts[index] += 1;
+            }
+            self.enemy_passer = Some(kick.robot_id);
+            self.reaction_start = None;
+        } else {
+            self.enemy_passer = None;
+            let velocities = world
+                .enemy_team
+                .all_robots()
+                .iter()
+                .map(|r| (r.id, r.state.velocity))
+                .collect();
+            self.reaction_start = Some((time, velocities));
+        }
+        self.their_kickoff_pending = false;
+    }
+
+    fn update_pass_receiver(&mut self, world: &World) {
+        let passer = match self.enemy_passer {
+            Some(id) => id,
+            None => return,
+        };
+        if world
+            .friendly_team
+            .all_robots()
+            .iter()
+            .any(|r| has_ball(r, &world.ball))
+        {
+            self.enemy_passer = None;
+            return;
+        }
+        if let Some(receiver) = world
+            .enemy_team
+            .all_robots()
+            .into_iter()
+            .find(|r| r.id != passer && has_ball(r, &world.ball))
+        {
+            let index = pass_area_index(&world.field, &receiver.state.position);
+            self.stats.pass_area_counts[index] += 1;
+            self.enemy_passer = None;
+        }
+    }
+
+    fn update_reaction(&mut self, world: &World, time: Instant) {
+        let (start_time, velocities) = match &self.reaction_start {
+            Some(r) => r,
+            None => return,
+        };
+        let elapsed = time.saturating_duration_since(*start_time).as_secs_f64();
+        if elapsed > MAX_REACTION_DELAY {
+            self.reaction_start = None;
+            return;
+        }
+        let reacted = world.enemy_team.all_robots().iter().any(|r| {
+            velocities.get(&r.id).map_or(false, |v| {
+                (r.state.velocity - *v).length() > REACTION_VELOCITY_CHANGE
+            })
+        });
+        if reacted {
+            self.stats.reaction_delay.add(elapsed);
+            self.reaction_start = None;
+        }
+    }
+
+    fn update_formation(&mut self, world: &World) {
+        if !world.game_state.playing()
+            || !world
+                .friendly_team
+                .all_robots()
+                .iter()
+                .any(|r| has_ball(r, &world.ball))
+        {
+            return;
+        }
+        let num_defenders = world
+            .enemy_team
+            .players()
+            .iter()
+            .filter(|r| {
+                (r.state.position.x - world.ball.position.x) * -world.field.friendly_side() > 0.0
+            })
+            .count();
+        self.stats.num_defenders.add(num_defenders as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_survive_saving_and_loading() {
+        let mut stats = OpponentStats::new();
+        for i in 0..MIN_SAMPLES {
+            stats.kick_speed.add(4.0 + i as f64 * 0.1);
+            stats.reaction_delay.add(0.2);
+            stats.pass_area_counts[4] += 1;
+            stats.kickoff_counts[kickoff_index(KickoffDirection::NegativeY)] += 1;
+        }
+        stats.max_speed = Some(2.5);
+        let loaded = OpponentStats::from_json(&stats.to_json()).unwrap();
+        assert_eq!(loaded, stats);
+        assert_eq!(
+            loaded.usual_kickoff_direction(),
+            Some(KickoffDirection::NegativeY)
+        );
+        let motion = loaded.enemy_motion();
+        assert_eq!(motion.max_speed, 2.5);
+        assert!((motion.reaction_delay - 0.2).abs() < 1.0e-9);
+        assert_eq!(
+            motion.max_acceleration,
+            DEFAULT_ENEMY_MOTION.max_acceleration
+        );
+    }
+
+    fn wait_for_load(model: &mut OpponentModel) {
+        let start = Instant::now();
+        while model.loading && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(1));
+            model.receive_loaded_stats();
+        }
+    }
+
+    #[test]
+    fn stats_are_saved_and_loaded_in_the_background() {
+        let dir = std::env::temp_dir().join(format!("opponent_model_test_{}", std::process::id()));
+        let dir = dir.to_str().unwrap();
+        let mut model = OpponentModel::new();
+        model.switch_team("Team A", dir);
+        wait_for_load(&mut model);
+        model.stats.max_speed = Some(2.5);
+        let stats = model.stats.clone();
+
+        // Switching back reads the file written when we switched away
+        model.switch_team("Team B", dir);
+        model.switch_team("Team A", dir);
+        assert_eq!(model.stats, OpponentStats::new());
+        wait_for_load(&mut model);
+        let _ = fs::remove_dir_all(dir);
+        assert_eq!(model.stats, stats);
+    }
+
+    #[test]
+    fn preferred_pass_area_contains_passes() {
+        let field = Field::ssl_div_b();
+        let receive_position = Point { x: -3.0, y: 2.0 };
+        let mut stats = OpponentStats::new();
+        assert!(stats.preferred_pass_area(&field).is_none());
+        for _ in 0..MIN_SAMPLES {
+            stats.pass_area_counts[pass_area_index(&field, &receive_position)] += 1;
+        }
+        let area = stats.preferred_pass_area(&field).unwrap();
+        assert!(area.contains(&receive_position));
+    }
+}
@@ -0,0 +1,209 @@
+use crate::gameplay::evaluation::passing::{
+    generate_random_passes, pass_gradient, score_pass, EnemyMotion, Pass,
+};
+use crate::gameplay::world::{Robot, World};
+use crate::geom::Vector;
+use crate::proto::config::Rules;
+use std::time::{Duration, Instant};
+
+const POPULATION_SIZE: usize = 30;
+// The worst passes are thrown away each tick and replaced with new random ones,
+// so we don't get stuck on local maxima
+const NUM_RESEEDED_PER_TICK: usize = 5;
+// Passes that converge to nearly the same place are duplicates, so only the best is kept
+const DUPLICATE_PASS_DIST: f64 = 0.3;
+const GRADIENT_STEP_SIZE: f64 = 0.1;
+// Limits how far a pass can move in one step, since the gradient can be very steep
+// near robots
+const MAX_END_STEP: f64 = 0.3;
+const MAX_SPEED_STEP: f64 = 0.5;
+const MIN_PASS_SPEED: f64 = 2.0;
+const MAX_PASS_SPEED: f64 = 5.5;
+const MAX_ITERATIONS_PER_TICK: usize = 5;
+
+// Keeps a set of good passes from the ball, improving them a little every tick
+pub struct PassOptimizer {
+    // Best first
+    passes: Vec<(Pass, f64)>,
+}
+
+impl PassOptimizer {
+    pub fn new() -> PassOptimizer {
+        PassOptimizer { passes: vec![] }
+    }
+
+    // Refines the passes for whoever is about to pass, giving up once the time budget is used
+    pub fn update(
+        &mut self,
+        world: &World,
+        passer_id: Option<usize>,
+        enemy_motion: &EnemyMotion,
+        rules: &Rules,
+        time_budget: Duration,
+    ) {
+        let start_time = Instant::now();
+        let field = &world.field;
+        let ball = world.ball.position;
+        let receivers: Vec<Robot> = world
+            .friendly_team
+            .players()
+            .into_iter()
+            .filter(|r| Some(r.id) != passer_id)
+            .cloned()
+            .collect();
+        let enemies: Vec<Robot> = world.enemy_team.all_robots().into_iter().cloned().collect();
+
+        let mut passes: Vec<Pass> = self.passes.drain(..).map(|(p, _)| p).collect();
+        passes.truncate(POPULATION_SIZE.saturating_sub(NUM_RESEEDED_PER_TICK));
+        let num_new = POPULATION_SIZE - passes.len();
+        passes.extend(generate_random_passes(
+            num_new,
+            &ball,
+            field,
+            (MIN_PASS_SPEED, MAX_PASS_SPEED),
+            &world.ball.friction,
+        ));
+
+        let field_area = field.touch_lines();
+        for p in passes.iter_mut() {
+            p.start = ball;
+        }
+        for _ in 0..MAX_ITERATIONS_PER_TICK {
+            if start_time.elapsed() > time_budget {
+                break;
+            }
+            for p in passes.iter_mut() {
+                let gradient = pass_gradient(p, field, &receivers, &enemies, enemy_motion, rules);
+                let end_step = Vector {
+                    x: gradient[0],
+                    y: gradient[1],
+                } * GRADIENT_STEP_SIZE;
+                let end_step = if end_step.length() > MAX_END_STEP {
+                    end_step.norm(MAX_END_STEP)
+                } else {
+                    end_step
+                };
+                p.end = field_area.clamp(&(p.end + end_step));
+                p.speed = (p.speed
+                    + (gradient[2] * GRADIENT_STEP_SIZE).clamp(-MAX_SPEED_STEP, MAX_SPEED_STEP))
+                .clamp(MIN_PASS_SPEED, MAX_PASS_SPEED);
+            }
+        }
+
+        let mut scored: Vec<(Pass, f64)> = passes
+            .into_iter()
+            .map(|p| {
+                let score = score_pass(&p, field, &receivers, &enemies, enemy_motion, rules);
+                (p, score)
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        for (p, score) in scored {
+            if self
+                .passes
+                .iter()
+                .all(|(q, _)| (p.end - q.end).length() > DUPLICATE_PASS_DIST)
+            {
+                self.passes.push((p, score));
+            }
+        }
+    }
+
+    // The best passes found so far and their scores, best first
+    pub fn best_passes(&self, num: usize) -> Vec<(Pass, f64)> {
+        self.passes.iter().take(num).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_config;
+    use crate::gameplay::evaluation::passing::DEFAULT_ENEMY_MOTION;
+    use crate::geom::{Angle, Point};
+    use crate::motion::KinematicState;
+    use crate::world::{Ball, BallFriction, Field, GameState, Team, TeamInfo};
+
+    fn robot(id: usize, position: Point) -> Robot {
+        Robot {
+            id,
+            state: KinematicState {
+                position,
+                orientation: Angle::zero(),
+                velocity: Vector::new(),
+                angular_velocity: Angle::zero(),
+            },
+        }
+    }
+
+    // One teammate is open and the other is surrounded by enemies
+    fn world() -> World {
+        let team_info = TeamInfo {
+            name: String::new(),
+            is_blue: true,
+            score: 0,
+            goalie_id: 0,
+            defending_positive_side: false,
+        };
+        let friendly = vec![
+            robot(0, Point { x: -4.3, y: 0.0 }),
+            robot(1, Point { x: -0.1, y: 0.0 }),
+            robot(2, Point { x: 1.5, y: 2.0 }),
+            robot(3, Point { x: 1.5, y: -2.0 }),
+        ];
+        let enemy = vec![
+            robot(0, Point { x: 1.5, y: -1.6 }),
+            robot(1, Point { x: 1.9, y: -2.0 }),
+            robot(2, Point { x: 1.1, y: -2.2 }),
+            robot(3, Point { x: 0.8, y: -1.0 }),
+        ];
+        World {
+            field: Field::ssl_div_b(),
+            ball: Ball {
+                position: Point::new(),
+                velocity: Vector::new(),
+                friction: BallFriction {
+                    sliding_deceleration: 3.0,
+                    rolling_deceleration: 0.5,
+                    rolling_speed: 2.0,
+                },
+            },
+            friendly_team: Team::new().with_robots(friendly).with_goalie(0).build(),
+            enemy_team: Team::new().with_robots(enemy).build(),
+            game_state: GameState::new(),
+            friendly_team_info: team_info.clone(),
+            enemy_team_info: team_info,
+        }
+    }
+
+    #[test]
+    fn best_pass_goes_to_open_teammate() {
+        let world = world();
+        let mut optimizer = PassOptimizer::new();
+        for _ in 0..20 {
+            optimizer.update(
+                &world,
+                Some(1),
+                &DEFAULT_ENEMY_MOTION,
+                &load_config().unwrap().rules,
+                Duration::from_secs(1),
+            );
+        }
+        let best = optimizer.best_passes(usize::MAX);
+        let (pass, score) = &best[0];
+        // The random passes it starts from are often poor, but there's an easy pass to find
+        assert!(*score > 0.9);
+        // Somewhere the open teammate can get to, well away from the covered one
+        let open = Point { x: 1.5, y: 2.0 };
+        let covered = Point { x: 1.5, y: -2.0 };
+        assert!((pass.end - open).length() < (pass.end - covered).length());
+        assert!(pass.end.y > 0.0);
+        // Duplicates are dropped, and the best comes first
+        for (i, (p, s)) in best.iter().enumerate() {
+            assert!(*s <= *score);
+            for (q, _) in &best[i + 1..] {
+                assert!((p.end - q.end).length() > DUPLICATE_PASS_DIST);
+            }
+        }
+    }
+}
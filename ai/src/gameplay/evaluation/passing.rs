@@ -1,7 +1,9 @@
-use crate::gameplay::world::{Field, Robot};
+use crate::gameplay::world::{Ball, Field, Robot};
 use crate::geom::{Angle, Point, Vector};
 use crate::math::{rect_sigmoid, sigmoid};
 use crate::motion::{bb_time_to_position, KinematicState};
+use crate::proto::config::Rules;
+use crate::world::BallFriction;
 use multiqueue2;
 use rand::Rng;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -11,132 +13,206 @@ use std::thread::sleep;
 use std::time::Duration;
 use std::time::Instant;
 
-#[derive(Clone)]
-struct Pass {
-    start: Point,
-    end: Point,
-    speed: f64,
-    time_offset: f64,
+// Passing robots are assumed to move like this when getting to the ball
+const ROBOT_MAX_ACCELERATION: f64 = 3.0;
+const ROBOT_MAX_SPEED: f64 = 3.0;
+const ROBOT_MAX_ANGULAR_SPEED: f64 = 4.0; // rad/s
+const NUM_INTERCEPT_STEPS: usize = 20;
+
+// How the robots trying to intercept a pass move
+#[derive(Clone, Copy, Debug)]
+pub struct EnemyMotion {
+    pub max_speed: f64,
+    pub max_acceleration: f64,
+    // How long it takes them to start moving once the ball is kicked
+    pub reaction_delay: f64,
+}
+
+// What we assume until we've learned better. They're probably about as fast as we are
+pub const DEFAULT_ENEMY_MOTION: EnemyMotion = EnemyMotion {
+    max_speed: ROBOT_MAX_SPEED,
+    max_acceleration: ROBOT_MAX_ACCELERATION,
+    reaction_delay: 0.3,
+};
+
+#[derive(Clone, Debug)]
+pub struct Pass {
+    pub start: Point,
+    pub end: Point,
+    // How fast the ball is kicked
+    pub speed: f64,
+    pub time_offset: f64,
+    pub friction: BallFriction,
 }
 
 impl Pass {
-    pub fn time_to_complete(&self) -> f64 {
-        let pass_dist = (self.end.x - self.start.x).hypot(self.end.y - self.start.y);
-        pass_dist / self.speed + self.time_offset
+    // The ball just after it's been kicked
+    pub fn ball(&self) -> Ball {
+        Ball {
+            position: self.start,
+            velocity: (self.end - self.start).norm(self.speed),
+            friction: self.friction,
+        }
+    }
+
+    // How long until the ball gets to the end of the pass. None if it stops before then
+    pub fn time_to_complete(&self) -> Option<f64> {
+        if (self.end - self.start).length() < 1.0e-6 {
+            return Some(self.time_offset);
+        }
+        self.ball()
+            .time_to_reach(&self.end)
+            .map(|t| t + self.time_offset)
     }
 }
 
-fn static_score(p: &Point, field: &Field) -> f64 {
+pub fn static_score(p: &Point, field: &Field) -> f64 {
     let on_field_score = rect_sigmoid(field.touch_lines(), p, 0.5);
     let enemy_defense_score = 1.0 - rect_sigmoid(field.enemy_defense_area(), p, 0.5);
     let friendly_defense_score = 1.0 - rect_sigmoid(field.friendly_defense_area(), p, 0.5);
-    let field_progress_score = sigmoid(p.x, 0.0, field.x_length) / 10.0 + 0.9;
+    let field_progress_score =
+        sigmoid(-field.friendly_side() * p.x, 0.0, field.x_length) / 10.0 + 0.9;
     on_field_score * enemy_defense_score * friendly_defense_score * field_progress_score
 }
 
-fn friendly_intercept_score(p: &Pass, robots: &Vec<Robot>) -> f64 {
-    if !robots.is_empty() {
-        let mut times_to_pos: Vec<f64> = Vec::new();
-        for r in robots {
-            times_to_pos.push(bb_time_to_position(
-                &r.state.position,
-                &r.state.velocity,
-                &p.end,
-                3.0,
-                3.0,
-            ));
-        }
-        // TODO: consider time it takes robot to rotate to face pass
-        // TODO: take into account robot radius when arriving at position
-        // The robot has to get there before the ball is < 1 radius away
-        let min_time_to_position = times_to_pos
-            .iter()
-            .fold(f64::INFINITY, |prev, curr| prev.min(*curr));
-        // If positive, a friendly robot can get to the pass position before the ball will arrive there
-        let time_to_position_diff = p.time_to_complete() - min_time_to_position;
-        sigmoid(time_to_position_diff, 0.5, 1.0)
+// How long the robot needs to be in position to receive the pass. It has to get its
+// dribbler to the end of the pass while turning to face the ball, and can do both at once
+fn time_to_receive(p: &Pass, r: &Robot, rules: &Rules) -> f64 {
+    let toward_passer = p.start - p.end;
+    let receive_position = if toward_passer.length() > 1.0e-6 {
+        p.end + toward_passer.norm(-rules.robot_max_radius)
     } else {
-        0.0
-    }
+        p.end
+    };
+    let move_time = bb_time_to_position(
+        &r.state.position,
+        &r.state.velocity,
+        &receive_position,
+        ROBOT_MAX_ACCELERATION,
+        ROBOT_MAX_SPEED,
+    );
+    let rotation = (toward_passer.orientation() - r.state.orientation).clamp_pos_neg_pi();
+    let rotate_time = rotation.radians().abs() / ROBOT_MAX_ANGULAR_SPEED;
+    move_time.max(rotate_time)
+}
+
+fn friendly_intercept_score(p: &Pass, robots: &[Robot], rules: &Rules) -> f64 {
+    let time_to_complete = match p.time_to_complete() {
+        Some(t) => t,
+        None => return 0.0,
+    };
+    let min_time_to_position = robots
+        .iter()
+        .map(|r| time_to_receive(p, r, rules))
+        .fold(f64::INFINITY, |prev, curr| prev.min(curr));
+    // If positive, a friendly robot can get to the pass position before the ball will arrive there
+    let time_to_position_diff = time_to_complete - min_time_to_position;
+    sigmoid(time_to_position_diff, 0.5, 1.0)
 }
 
-fn enemy_min_tim_to_intercept(p: &Pass, r: &Robot) -> f64 {
-    const REACTION_DELAY: f64 = 0.3;
-    const NUM_STEPS: usize = 20;
-    const ROBOT_RADIUS: f64 = 0.18;
-    let x_incr = (p.end.x - p.start.x) / NUM_STEPS as f64;
-    let y_incr = (p.end.y - p.start.y) / NUM_STEPS as f64;
+// How much time the enemy has to spare when getting in the way of the ball, at the
+// point along the pass where it's easiest for them. Negative if they can't make it
+fn enemy_min_time_to_intercept(p: &Pass, r: &Robot, motion: &EnemyMotion, rules: &Rules) -> f64 {
+    let time_to_complete = match p.time_to_complete() {
+        Some(t) => t - p.time_offset,
+        None => return f64::INFINITY,
+    };
+    let ball = p.ball();
     let mut min_diff = f64::INFINITY;
-    for i in 0..NUM_STEPS {
-        let pos = Point {
-            x: p.start.x + i as f64 * x_incr + ROBOT_RADIUS,
-            y: p.start.x + i as f64 * y_incr + ROBOT_RADIUS,
+    for i in 0..=NUM_INTERCEPT_STEPS {
+        let t = i as f64 / NUM_INTERCEPT_STEPS as f64 * time_to_complete;
+        let ball_position = ball.position_at(t);
+        // The enemy only has to touch the ball, not get their center onto it
+        let to_enemy = r.state.position - ball_position;
+        let block_dist = rules.robot_max_radius + rules.ball_max_radius;
+        let block_position = if to_enemy.length() > block_dist {
+            ball_position + to_enemy.norm(block_dist)
+        } else {
+            r.state.position
         };
-        // TODO: Take into consideration the robot radius
-        let ttp = bb_time_to_position(&r.state.position, &r.state.velocity, &pos, 3.0, 3.0);
-        let diff = ttp - p.time_to_complete();
+        let time_to_position = bb_time_to_position(
+            &r.state.position,
+            &r.state.velocity,
+            &block_position,
+            motion.max_acceleration,
+            motion.max_speed,
+        );
+        let diff = time_to_position - (t + p.time_offset);
         min_diff = min_diff.min(diff);
     }
-    min_diff + REACTION_DELAY
+    min_diff + motion.reaction_delay
 }
 
-fn enemy_intercept_score(p: &Pass, robots: &Vec<Robot>) -> f64 {
-    if !robots.is_empty() {
-        let mut enemy_intercept_times: Vec<f64> = Vec::new();
-        for r in robots {
-            enemy_intercept_times.push(enemy_min_tim_to_intercept(p, r));
-        }
-        let min_intercept_time = enemy_intercept_times
-            .iter()
-            .fold(f64::INFINITY, |prev, curr| prev.min(*curr));
-        // If positive, the ball should reach the pass destination before an enemy robot can
-        // intercept. If the value is negative at all, this means an enemy robot can intercept
-        // the pass (possibly before it arrives at it's destination)
-        let intercept_time_diff = p.time_to_complete() - min_intercept_time;
-        sigmoid(min_intercept_time, 0.2, 0.4)
-    } else {
-        1.0
-    }
+pub fn enemy_intercept_score(
+    p: &Pass,
+    robots: &[Robot],
+    motion: &EnemyMotion,
+    rules: &Rules,
+) -> f64 {
+    let min_intercept_time = robots
+        .iter()
+        .map(|r| enemy_min_time_to_intercept(p, r, motion, rules))
+        .fold(f64::INFINITY, |prev, curr| prev.min(curr));
+    // If positive, the ball gets past every point on its path before an enemy robot can
+    // get there. Negative means an enemy robot can intercept the pass
+    sigmoid(min_intercept_time, 0.2, 0.4)
 }
 
-fn score_pass(
+pub fn score_pass(
     p: &Pass,
     field: &Field,
-    friendly_robots: &Vec<Robot>,
-    enemy_robots: &Vec<Robot>,
+    friendly_robots: &[Robot],
+    enemy_robots: &[Robot],
+    enemy_motion: &EnemyMotion,
+    rules: &Rules,
 ) -> f64 {
     let static_score = static_score(&p.end, field);
-    let friendly_score = friendly_intercept_score(&p, friendly_robots);
-    let enemy_score = enemy_intercept_score(&p, enemy_robots);
+    let friendly_score = friendly_intercept_score(&p, friendly_robots, rules);
+    let enemy_score = enemy_intercept_score(&p, enemy_robots, enemy_motion, rules);
     static_score * friendly_score * enemy_score
 }
 
-fn generate_random_passes(num: usize) -> Vec<Pass> {
+// Passes from the start point to anywhere on the field
+pub fn generate_random_passes(
+    num: usize,
+    start: &Point,
+    field: &Field,
+    speed_range: (f64, f64),
+    friction: &BallFriction,
+) -> Vec<Pass> {
+    let mut rng = rand::thread_rng();
     let mut result: Vec<Pass> = Vec::new();
     for _ in 0..num {
         result.push(Pass {
-            start: Point {
-                x: rand::thread_rng().gen(),
-                y: rand::thread_rng().gen(),
-            },
+            start: *start,
             end: Point {
-                x: rand::thread_rng().gen(),
-                y: rand::thread_rng().gen(),
+                x: rng.gen_range(-field.x_length / 2.0..field.x_length / 2.0),
+                y: rng.gen_range(-field.y_length / 2.0..field.y_length / 2.0),
             },
-            speed: 4.0,
+            speed: rng.gen_range(speed_range.0..speed_range.1),
             time_offset: 0.15,
+            friction: *friction,
         })
     }
     result
 }
 
-fn pass_gradient(
+pub fn pass_gradient(
     p: &Pass,
     field: &Field,
-    friendly_robots: &Vec<Robot>,
-    enemy_robots: &Vec<Robot>,
+    friendly_robots: &[Robot],
+    enemy_robots: &[Robot],
+    enemy_motion: &EnemyMotion,
+    rules: &Rules,
 ) -> Vec<f64> {
-    let base = score_pass(&p, &field, &friendly_robots, &enemy_robots);
+    let base = score_pass(
+        &p,
+        &field,
+        &friendly_robots,
+        &enemy_robots,
+        enemy_motion,
+        rules,
+    );
     let diff = 1.0e-3;
     let mut p1 = p.clone();
     p1.end.x += diff;
@@ -148,25 +224,143 @@ fn pass_gradient(
     p4.time_offset += diff;
 
     vec![
-        (score_pass(&p1, &field, &friendly_robots, &enemy_robots) - base) / diff,
-        (score_pass(&p2, &field, &friendly_robots, &enemy_robots) - base) / diff,
-        (score_pass(&p3, &field, &friendly_robots, &enemy_robots) - base) / diff,
-        (score_pass(&p4, &field, &friendly_robots, &enemy_robots) - base) / diff,
+        (score_pass(
+            &p1,
+            &field,
+            &friendly_robots,
+            &enemy_robots,
+            enemy_motion,
+            rules,
+        ) - base)
+            / diff,
+        (score_pass(
+            &p2,
+            &field,
+            &friendly_robots,
+            &enemy_robots,
+            enemy_motion,
+            rules,
+        ) - base)
+            / diff,
+        (score_pass(
+            &p3,
+            &field,
+            &friendly_robots,
+            &enemy_robots,
+            enemy_motion,
+            rules,
+        ) - base)
+            / diff,
+        (score_pass(
+            &p4,
+            &field,
+            &friendly_robots,
+            &enemy_robots,
+            enemy_motion,
+            rules,
+        ) - base)
+            / diff,
     ]
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::load_config;
     use serde_json;
     use serde_json::json;
     use serde_json::Result;
     use std::fs;
 
+    fn friction() -> BallFriction {
+        BallFriction {
+            sliding_deceleration: 3.0,
+            rolling_deceleration: 0.5,
+            rolling_speed: 2.0,
+        }
+    }
+
+    fn pass(start: Point, end: Point, speed: f64) -> Pass {
+        Pass {
+            start,
+            end,
+            speed,
+            time_offset: 0.15,
+            friction: friction(),
+        }
+    }
+
+    fn rules() -> Rules {
+        load_config().unwrap().rules
+    }
+
+    fn stationary_robot(position: Point, orientation: Angle) -> Robot {
+        Robot {
+            id: 0,
+            state: KinematicState {
+                position,
+                orientation,
+                velocity: Vector::new(),
+                angular_velocity: Angle::zero(),
+            },
+        }
+    }
+
+    #[test]
+    fn pass_time_includes_friction() {
+        // Slides from 4 m/s down to 2 m/s at 3 m/s^2, covering exactly 2 m in 2/3 s
+        let p = pass(Point::new(), Point { x: 2.0, y: 0.0 }, 4.0);
+        let t = p.time_to_complete().unwrap();
+        assert!((t - (2.0 / 3.0 + 0.15)).abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn pass_that_stops_short_scores_zero() {
+        // Only rolls 1 m before stopping
+        let p = pass(Point::new(), Point { x: 3.0, y: 0.0 }, 1.0);
+        assert!(p.time_to_complete().is_none());
+        let receiver = stationary_robot(Point { x: 3.0, y: 0.0 }, Angle::half());
+        assert_eq!(
+            score_pass(
+                &p,
+                &Field::ssl_div_b(),
+                &vec![receiver],
+                &vec![],
+                &DEFAULT_ENEMY_MOTION,
+                &rules()
+            ),
+            0.0
+        );
+    }
+
+    #[test]
+    fn receiver_facing_away_needs_time_to_turn() {
+        let p = pass(Point::new(), Point { x: 2.0, y: 0.0 }, 4.0);
+        let receive_position = Point {
+            x: 2.0 + rules().robot_max_radius,
+            y: 0.0,
+        };
+        let facing_ball = stationary_robot(receive_position, Angle::half());
+        let facing_away = stationary_robot(receive_position, Angle::zero());
+        assert!(time_to_receive(&p, &facing_ball, &rules()) < 1.0e-6);
+        let expected = std::f64::consts::PI / ROBOT_MAX_ANGULAR_SPEED;
+        assert!((time_to_receive(&p, &facing_away, &rules()) - expected).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn enemy_on_pass_path_intercepts() {
+        // The pass isn't along y = 0, which used to hide enemies in the way
+        let p = pass(Point { x: 0.0, y: 1.0 }, Point { x: 3.0, y: 1.0 }, 4.0);
+        let blocking = stationary_robot(Point { x: 1.5, y: 1.0 }, Angle::zero());
+        let far_away = stationary_robot(Point { x: 1.5, y: -3.0 }, Angle::zero());
+        assert!(enemy_intercept_score(&p, &vec![blocking], &DEFAULT_ENEMY_MOTION, &rules()) < 0.05);
+        assert!(enemy_intercept_score(&p, &vec![far_away], &DEFAULT_ENEMY_MOTION, &rules()) > 0.9);
+    }
+
     #[test]
     fn profile_score_function() {
-        let passes = generate_random_passes(18);
         let field = Field::ssl_div_b();
+        let passes = generate_random_passes(18, &Point::new(), &field, (1.0, 6.0), &friction());
         let friendly_robots: Vec<Robot> = vec![
             Robot {
                 id: 0,
@@ -244,9 +438,17 @@ mod tests {
             },
         ];
 
+        let rules = rules();
         let start = Instant::now();
         for p in &passes {
-            let grad = pass_gradient(p, &field, &friendly_robots, &enemy_robots);
+            let grad = pass_gradient(
+                p,
+                &field,
+                &friendly_robots,
+                &enemy_robots,
+                &DEFAULT_ENEMY_MOTION,
+                &rules,
+            );
             if grad[0] == 0.392 {
                 println!("preventing too much compiler optimization :)");
             }
@@ -342,6 +544,7 @@ mod tests {
         // let mut x = [[0.0; X_DIVISIONS +1]; Y_DIVISIONS +1];
         // let mut y = [[0.0; X_DIVISIONS +1]; Y_DIVISIONS +1];
         // let mut z = [[0.0; X_DIVISIONS +1]; Y_DIVISIONS +1];
+        let rules = rules();
         let mut x: Vec<Vec<f64>> = vec![];
         let mut y: Vec<Vec<f64>> = vec![];
         let mut z: Vec<Vec<f64>> = vec![];
@@ -362,8 +565,16 @@ mod tests {
                     end: Point { x: x_pos, y: y_pos },
                     speed,
                     time_offset,
+                    friction: friction(),
                 };
-                z[yy].push(score_pass(&p, &field, &friendly_robots, &enemy_robots));
+                z[yy].push(score_pass(
+                    &p,
+                    &field,
+                    &friendly_robots,
+                    &enemy_robots,
+                    &DEFAULT_ENEMY_MOTION,
+                    &rules,
+                ));
             }
         }
 
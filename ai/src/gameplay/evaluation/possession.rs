@@ -0,0 +1,40 @@
+use crate::gameplay::world::{Robot, World};
+use crate::motion::dribble::has_ball;
+
+// How much closer to the ball one team has to be than the other to have possession.
+// When neither team is clearly closer, nobody has possession
+const POSSESSION_DIST_MARGIN: f64 = 0.2;
+
+fn closest_dist_to_ball(robots: Vec<&Robot>, world: &World) -> f64 {
+    robots
+        .iter()
+        .map(|r| (r.state.position - world.ball.position).length())
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn has_possession(team: Vec<&Robot>, other_team: Vec<&Robot>, world: &World) -> bool {
+    if other_team.iter().any(|r| has_ball(r, &world.ball)) {
+        return false;
+    }
+    if team.iter().any(|r| has_ball(r, &world.ball)) {
+        return true;
+    }
+    closest_dist_to_ball(team, world) + POSSESSION_DIST_MARGIN
+        < closest_dist_to_ball(other_team, world)
+}
+
+pub fn friendly_has_possession(world: &World) -> bool {
+    has_possession(
+        world.friendly_team.all_robots(),
+        world.enemy_team.all_robots(),
+        world,
+    )
+}
+
+pub fn enemy_has_possession(world: &World) -> bool {
+    has_possession(
+        world.enemy_team.all_robots(),
+        world.friendly_team.all_robots(),
+        world,
+    )
+}
@@ -0,0 +1,329 @@
+use crate::gameplay::evaluation::best_shot::best_shot_on_our_goal;
+use crate::gameplay::world::{Robot, World};
+use crate::geom::Segment;
+use crate::math::sigmoid;
+use crate::motion::KinematicState;
+use crate::proto::config::Rules;
+use std::collections::HashMap;
+use std::time::Instant;
+
+// An enemy this close to the ball has it, or is about to
+const BALL_CARRIER_DIST: f64 = 0.3;
+// A pass lane is blocked if one of our robots is this close to it
+const PASS_LANE_BLOCK_DIST: f64 = 0.15;
+// Enemies further than this behind the ball are hanging back to defend
+const DEFENDER_DIST_BEHIND_BALL: f64 = 0.5;
+// Most of the field is in range of a shot, so threat falls off slowly with distance
+const THREAT_DIST_OFFSET: f64 = 4.0;
+const THREAT_DIST_WIDTH: f64 = 6.0;
+const THREAT_SPEED_WIDTH: f64 = 4.0;
+const POSITION_WEIGHT: f64 = 0.4;
+const VELOCITY_WEIGHT: f64 = 0.2;
+const SHOT_WEIGHT: f64 = 0.4;
+// Enemies we're already cutting off from the ball are much less dangerous
+const BLOCKED_PASS_LANE_FACTOR: f64 = 0.3;
+
+// What we assume until we've seen them move
+const DEFAULT_MAX_SPEED: f64 = 1.0;
+const DEFAULT_MAX_ACCELERATION: f64 = 1.0;
+// Anything faster than this is a vision glitch
+const MAX_PLAUSIBLE_SPEED: f64 = 5.0;
+const MAX_PLAUSIBLE_ACCELERATION: f64 = 10.0;
+// Only motion that lasts this many ticks in a row counts, so single noisy
+// frames don't inflate the estimates
+const NUM_SUSTAINED_SAMPLES: usize = 3;
+// Samples further apart than this are from a robot that went missing for a while
+const MAX_SAMPLE_PERIOD: f64 = 0.1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnemyRole {
+    Goalie,
+    // Has the ball or is about to
+    BallCarrier,
+    // Could be passed the ball
+    Receiver,
+    // Staying back or cut off from the ball
+    Defender,
+}
+
+#[derive(Clone, Debug)]
+pub struct EnemyThreat {
+    pub id: usize,
+    pub role: EnemyRole,
+    // Between 0 and 1, higher is more dangerous
+    pub score: f64,
+}
+
+struct RobotHistory {
+    time: Instant,
+    state: KinematicState,
+    // The most recent samples, newest last
+    speeds: Vec<f64>,
+    accelerations: Vec<f64>,
+}
+
+// Keeps track of what each enemy robot is doing, and how fast they are able to move
+pub struct EnemyTracker {
+    history: HashMap<usize, RobotHistory>,
+    max_speed: f64,
+    max_acceleration: f64,
+    num_motion_samples: usize,
+    // Most dangerous first
+    threats: Vec<EnemyThreat>,
+}
+
+fn push_sample(samples: &mut Vec<f64>, sample: f64) -> Option<f64> {
+    samples.push(sample);
+    if samples.len() > NUM_SUSTAINED_SAMPLES {
+        samples.remove(0);
+    }
+    if samples.len() == NUM_SUSTAINED_SAMPLES {
+        samples.iter().copied().reduce(f64::min)
+    } else {
+        None
+    }
+}
+
+fn pass_lane_open(world: &World, enemy: &Robot) -> bool {
+    let lane = Segment::new(world.ball.position, enemy.state.position);
+    world
+        .friendly_team
+        .all_robots()
+        .iter()
+        .all(|r| lane.dist_to_point(&r.state.position) > PASS_LANE_BLOCK_DIST)
+}
+
+fn classify(world: &World, enemy: &Robot, carrier_id: Option<usize>) -> EnemyRole {
+    if world.enemy_team.goalie_id() == Some(enemy.id) {
+        return EnemyRole::Goalie;
+    }
+    if carrier_id == Some(enemy.id) {
+        return EnemyRole::BallCarrier;
+    }
+    // The enemy attacks towards our goal
+    let behind_ball = (enemy.state.position.x - world.ball.position.x)
+        * -world.field.friendly_side()
+        > DEFENDER_DIST_BEHIND_BALL;
+    if behind_ball || !pass_lane_open(world, enemy) {
+        EnemyRole::Defender
+    } else {
+        EnemyRole::Receiver
+    }
+}
+
+fn threat_score(world: &World, enemy: &Robot, role: EnemyRole, rules: &Rules) -> f64 {
+    if role == EnemyRole::Goalie {
+        return 0.0;
+    }
+    let goal = world.field.friendly_goal_center();
+    let to_goal = goal - enemy.state.position;
+    let position_score = sigmoid(-to_goal.length(), -THREAT_DIST_OFFSET, THREAT_DIST_WIDTH);
+    let speed_to_goal = enemy.state.velocity.dot(&to_goal.norm(1.0));
+    let velocity_score = sigmoid(speed_to_goal, 0.0, THREAT_SPEED_WIDTH);
+    let shot_score = best_shot_on_our_goal(world, &enemy.state.position, Some(enemy.id), rules)
+        .map_or(0.0, |s| s.score_probability);
+    let score = POSITION_WEIGHT * position_score
+        + VELOCITY_WEIGHT * velocity_score
+        + SHOT_WEIGHT * shot_score;
+    match role {
+        EnemyRole::BallCarrier | EnemyRole::Receiver => score,
+        _ => score * BLOCKED_PASS_LANE_FACTOR,
+    }
+}
+
+impl EnemyTracker {
+    pub fn new() -> EnemyTracker {
+        EnemyTracker {
+            history: HashMap::new(),
+            max_speed: DEFAULT_MAX_SPEED,
+            max_acceleration: DEFAULT_MAX_ACCELERATION,
+            num_motion_samples: 0,
+            threats: vec![],
+        }
+    }
+
+    pub fn update(&mut self, world: &World, rules: &Rules, time: Instant) {
+        let enemies = world.enemy_team.all_robots();
+        self.history
+            .retain(|id, _| enemies.iter().any(|r| r.id == *id));
+        for r in &enemies {
+            self.update_motion(r, time);
+        }
+
+        let ball = world.ball.position;
+        let carrier_id = enemies
+            .iter()
+            .filter(|r| (r.state.position - ball).length() < BALL_CARRIER_DIST)
+            .min_by(|a, b| {
+                (a.state.position - ball)
+                    .length()
+                    .total_cmp(&(b.state.position - ball).length())
+            })
+            .map(|r| r.id);
+        self.threats = enemies
+            .iter()
+            .map(|r| {
+                let role = classify(world, r, carrier_id);
+                EnemyThreat {
+                    id: r.id,
+                    role,
+                    score: threat_score(world, r, role, rules),
+                }
+            })
+            .collect();
+        self.threats.sort_by(|a, b| b.score.total_cmp(&a.score));
+    }
+
+    fn update_motion(&mut self, robot: &Robot, time: Instant) {
+        let speed = robot.state.velocity.length();
+        let history = match self.history.get_mut(&robot.id) {
+            Some(h) => h,
+            None => {
+                self.history.insert(
+                    robot.id,
+                    RobotHistory {
+                        time,
+                        state: robot.state.clone(),
+                        speeds: vec![],
+                        accelerations: vec![],
+                    },
+                );
+                return;
+            }
+        };
+        let dt = (time - history.time).as_secs_f64();
+        if dt <= 0.0 {
+            return;
+        }
+        if dt < MAX_SAMPLE_PERIOD {
+            let acceleration = (robot.state.velocity - history.state.velocity).length() / dt;
+            if let Some(s) = push_sample(&mut history.speeds, speed) {
+                self.num_motion_samples += 1;
+                if s < MAX_PLAUSIBLE_SPEED {
+                    self.max_speed = self.max_speed.max(s);
+                }
+            }
+            if let Some(a) = push_sample(&mut history.accelerations, acceleration) {
+                if a < MAX_PLAUSIBLE_ACCELERATION {
+                    self.max_acceleration = self.max_acceleration.max(a);
+                }
+            }
+        } else {
+            history.speeds.clear();
+            history.accelerations.clear();
+        }
+        history.time = time;
+        history.state = robot.state.clone();
+    }
+
+    // The fastest the enemy has been seen moving
+    pub fn max_speed(&self) -> f64 {
+        self.max_speed
+    }
+
+    pub fn max_acceleration(&self) -> f64 {
+        self.max_acceleration
+    }
+
+    // How many times we've seen the enemy moving, across all their robots
+    pub fn num_motion_samples(&self) -> usize {
+        self.num_motion_samples
+    }
+
+    // Every enemy robot, most dangerous first
+    pub fn threats(&self) -> &Vec<EnemyThreat> {
+        &self.threats
+    }
+
+    pub fn role(&self, id: usize) -> Option<EnemyRole> {
+        self.threats.iter().find(|t| t.id == id).map(|t| t.role)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_config;
+    use crate::geom::{Angle, Point, Vector};
+    use crate::world::{Ball, BallFriction, Field, GameState, Team, TeamInfo};
+    use std::time::Duration;
+
+    fn robot(id: usize, position: Point, velocity: Vector) -> Robot {
+        Robot {
+            id,
+            state: KinematicState {
+                position,
+                orientation: Angle::zero(),
+                velocity,
+                angular_velocity: Angle::zero(),
+            },
+        }
+    }
+
+    fn world(friendly: Vec<Robot>, enemy: Vec<Robot>, ball: Point) -> World {
+        let team_info = TeamInfo {
+            name: String::new(),
+            is_blue: true,
+            score: 0,
+            goalie_id: 0,
+            defending_positive_side: false,
+        };
+        World {
+            field: Field::ssl_div_b(),
+            ball: Ball {
+                position: ball,
+                velocity: Vector::new(),
+                friction: BallFriction {
+                    sliding_deceleration: 3.0,
+                    rolling_deceleration: 0.5,
+                    rolling_speed: 2.0,
+                },
+            },
+            friendly_team: Team::new().with_robots(friendly).build(),
+            enemy_team: Team::new().with_robots(enemy).with_goalie(0).build(),
+            game_state: GameState::new(),
+            friendly_team_info: team_info.clone(),
+            enemy_team_info: team_info,
+        }
+    }
+
+    #[test]
+    fn roles_and_ranking() {
+        let ball = Point { x: 1.0, y: 0.0 };
+        let enemies = vec![
+            robot(0, Point { x: 4.4, y: 0.0 }, Vector::new()),
+            robot(1, Point { x: 1.2, y: 0.0 }, Vector::new()),
+            // Open for a pass close to our goal
+            robot(2, Point { x: -2.5, y: 1.0 }, Vector::new()),
+            // Cut off from the ball by one of our robots
+            robot(3, Point { x: -2.5, y: -1.5 }, Vector::new()),
+            robot(4, Point { x: 3.0, y: 2.0 }, Vector::new()),
+        ];
+        let blocker = robot(0, Point { x: -0.75, y: -0.75 }, Vector::new());
+        let world = world(vec![blocker], enemies, ball);
+        let mut tracker = EnemyTracker::new();
+        tracker.update(&world, &load_config().unwrap().rules, Instant::now());
+
+        assert_eq!(tracker.role(0), Some(EnemyRole::Goalie));
+        assert_eq!(tracker.role(1), Some(EnemyRole::BallCarrier));
+        assert_eq!(tracker.role(2), Some(EnemyRole::Receiver));
+        assert_eq!(tracker.role(3), Some(EnemyRole::Defender));
+        assert_eq!(tracker.role(4), Some(EnemyRole::Defender));
+        assert_eq!(tracker.threats()[0].id, 2);
+        assert_eq!(tracker.threats().last().unwrap().id, 0);
+    }
+
+    #[test]
+    fn max_speed_ignores_single_frame_glitches() {
+        let mut tracker = EnemyTracker::new();
+        let rules = load_config().unwrap().rules;
+        let start = Instant::now();
+        let speeds = [2.0, 2.0, 2.0, 2.0, 4.5, 2.0, 2.0];
+        for (i, speed) in speeds.iter().enumerate() {
+            let enemy = robot(1, Point::new(), Vector { x: *speed, y: 0.0 });
+            let world = world(vec![], vec![enemy], Point { x: 2.0, y: 2.0 });
+            tracker.update(&world, &rules, start + Duration::from_millis(16 * i as u64));
+        }
+        assert!((tracker.max_speed() - 2.0).abs() < 1.0e-9);
+    }
+}
@@ -1,60 +1,120 @@
-mod evaluation;
+pub mod evaluation;
 mod play;
+mod skill;
 mod tactic;
 pub mod world;
 
 use crate::communication::buffer::{NodeReceiver, NodeSender};
 use crate::communication::node::Node;
+use crate::config::{ConfigWatcher, CONFIG_FILEPATH};
+use crate::gameplay::evaluation::heatmap::{Heatmap, HeatmapCache, HeatmapPublisher};
+use crate::gameplay::evaluation::opponent_model::OpponentModel;
+use crate::gameplay::evaluation::pass_optimizer::PassOptimizer;
+use crate::gameplay::evaluation::threats::EnemyTracker;
 use crate::gameplay::world::{Robot, World};
+use crate::motion::constraints::MotionConstraints;
+use crate::motion::dribble::{has_ball, DribbleTracker};
+use crate::motion::planner::stopping_trajectory;
 use crate::motion::Trajectory;
-use crate::proto::config::Config;
+use crate::proto::config::{Config, Rules, Strategy};
 use crate::run_nodes_in_parallel_threads;
 use crate::world::World as PartialWorld;
+use crate::world::{Division, DivisionRules};
 use multiqueue2;
 use munkres::WeightMatrix;
 use play::{Play, RequestedTactics};
 use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 use strum::IntoEnumIterator;
-use tactic::Tactic;
+use tactic::{ActiveTactic, Tactic, TacticStatus};
 
 pub struct Input {
     pub world: NodeReceiver<PartialWorld>,
 }
 pub struct Output {
     pub trajectories: NodeSender<HashMap<usize, Trajectory>>,
+    pub heatmaps: NodeSender<Vec<Arc<Heatmap>>>,
 }
 
 pub struct Gameplay {
     input: Input,
-    output: Output,
+    trajectories: NodeSender<HashMap<usize, Trajectory>>,
+    heatmap_publisher: HeatmapPublisher,
     state: State,
+    config: Arc<Config>,
+    config_updates: mpsc::Receiver<Arc<Config>>,
+    last_heatmap_publish_time: Instant,
+}
+
+// Extra cost for giving a robot a different job to the one it's already doing, so robots
+// don't swap roles every time the costs are close
+const SWITCHING_COST: f64 = 0.5;
+// Robots that only just started their current job should almost never be moved off it
+const MIN_HOLD_TIME: Duration = Duration::from_millis(500);
+const HELD_SWITCHING_COST: f64 = 10.0;
+// Plays run for at least this long unless they have to stop, so we don't keep changing strategy
+const MIN_PLAY_RUN_TIME: Duration = Duration::from_secs(1);
+const PLAY_SWITCH_MARGIN: f64 = 0.1;
+// How much of each tick can be spent improving passes
+const PASS_OPTIMIZER_TIME_BUDGET: Duration = Duration::from_millis(5);
+// Heatmaps are only sent to the GUI this often, since computing all of them is slow even
+// off the gameplay thread
+const HEATMAP_PUBLISH_PERIOD: Duration = Duration::from_millis(500);
+
+fn switching_cost(
+    tactic: &Tactic,
+    robot_id: usize,
+    previous: &HashMap<usize, ActiveTactic>,
+) -> f64 {
+    match previous.get(&robot_id) {
+        Some(a) if a.tactic().is_same_role(tactic) => 0.0,
+        Some(a) if a.running_time() < MIN_HOLD_TIME => HELD_SWITCHING_COST,
+        Some(_) => SWITCHING_COST,
+        None => 0.0,
+    }
+}
+
+fn assignment_cost(
+    tactic: &Tactic,
+    robot: &Robot,
+    world: &World,
+    previous: &HashMap<usize, ActiveTactic>,
+) -> f64 {
+    tactic.robot_assignment_cost(robot, world) + switching_cost(tactic, robot.id, previous)
 }
 
 fn optimized_tactic_assignment(
     mut tactics: Vec<Tactic>,
     robots: Vec<&Robot>,
+    world: &World,
+    previous: &HashMap<usize, ActiveTactic>,
 ) -> HashMap<usize, Tactic> {
     let mut assignments: HashMap<usize, Tactic> = HashMap::new();
-    if robots.len() > tactics.len() {
-        println!("More tactics requested to optimize than robots available");
-        tactics.truncate(robots.len());
-    }
-    if !robots.is_empty() {
+    // Tactics are listed most important first, so drop the ones at the end if we're short
+    tactics.truncate(robots.len());
+    if !robots.is_empty() && !tactics.is_empty() {
+        // The solver needs a square matrix, so pad it with zero-cost dummy tactics or robots
+        let size = tactics.len().max(robots.len());
         let mut tactic_assignment_weights: Vec<f64> = vec![];
         // Each row holds the cost of assigning a robot to each tactic
-        for t in &tactics {
-            for r in &robots {
-                // println!("{:?} : {} : {}", t, r.id, t.robot_assignment_cost(r));
-                tactic_assignment_weights.push(t.robot_assignment_cost(r));
+        for i in 0..size {
+            for j in 0..size {
+                match (tactics.get(i), robots.get(j)) {
+                    (Some(t), Some(r)) => {
+                        // println!("{:?} : {} : {}", t, r.id, t.robot_assignment_cost(r, world));
+                        tactic_assignment_weights.push(assignment_cost(t, r, world, previous))
+                    }
+                    _ => tactic_assignment_weights.push(0.0),
+                }
             }
         }
         let mut tactic_assignment_weights =
-            WeightMatrix::from_row_vec(tactics.len(), tactic_assignment_weights);
+            WeightMatrix::from_row_vec(size, tactic_assignment_weights);
         // println!("weights: {:?}", tactic_assignment_weights.as_slice());
         let munkres_assignments = munkres::solve_assignment(&mut tactic_assignment_weights)
             .unwrap_or_else(|e| {
@@ -62,10 +122,10 @@ fn optimized_tactic_assignment(
                 vec![]
             });
         for a in munkres_assignments {
-            let id = robots[a.column].id;
-            let t = tactics[a.row].clone();
-            // println!("Assigned {:?} to {}", t, id);
-            assignments.insert(id, t);
+            if let (Some(t), Some(r)) = (tactics.get(a.row), robots.get(a.column)) {
+                // println!("Assigned {:?} to {}", t, r.id);
+                assignments.insert(r.id, t.clone());
+            }
         }
     }
     assignments
@@ -74,13 +134,15 @@ fn optimized_tactic_assignment(
 fn greedy_tactic_assignment(
     tactics: Vec<Tactic>,
     mut robots: &mut HashMap<usize, &Robot>,
+    world: &World,
+    previous: &HashMap<usize, ActiveTactic>,
 ) -> HashMap<usize, Tactic> {
     let mut assignments: HashMap<usize, Tactic> = HashMap::new();
     for t in tactics {
         if !robots.is_empty() {
             let (id, cost) = robots
                 .iter()
-                .map(|(_, r)| (r.id, t.robot_assignment_cost(r)))
+                .map(|(_, r)| (r.id, assignment_cost(&t, r, world, previous)))
                 .min_by(|(id1, c1), (id2, c2)| c1.total_cmp(c2))
                 .unwrap();
             assignments.insert(id, t);
@@ -93,65 +155,200 @@ fn greedy_tactic_assignment(
     assignments
 }
 
+// The division limits how many robots we may have on the field, so anything past that
+// is told to stop. The goalie always plays, then the lowest ids. Returns the robots that
+// play and the ones that are left out
+fn allowed_robots(world: &World, max_robots: usize) -> (Vec<&Robot>, Vec<&Robot>) {
+    let mut robots = world.friendly_team.players();
+    robots.sort_by_key(|r| r.id);
+    if let Some(goalie) = world.friendly_team.goalie() {
+        robots.insert(0, goalie);
+    }
+    let left_out = robots.split_off(max_robots.min(robots.len()));
+    (robots, left_out)
+}
+
+// Returns the assignments, and which robot got the kicker tactic. Groups are assigned in
+// priority order: goalie, kicker, greedy and then optimized
 fn assign_robots_to_tactics(
     tactics: RequestedTactics,
     mut robots: HashMap<usize, &Robot>,
-) -> HashMap<usize, Tactic> {
-    let mut assignments = greedy_tactic_assignment(tactics.greedy, &mut robots);
+    world: &World,
+    previous: &HashMap<usize, ActiveTactic>,
+) -> (HashMap<usize, Tactic>, Option<usize>) {
+    let mut assignments: HashMap<usize, Tactic> = HashMap::new();
+    if let (Some(t), Some(id)) = (tactics.goalie, world.friendly_team.goalie_id()) {
+        if robots.remove(&id).is_some() {
+            assignments.insert(id, t);
+        }
+    }
+    let kicker_assignment = match tactics.kicker {
+        Some(t) => greedy_tactic_assignment(vec![t], &mut robots, world, previous),
+        None => HashMap::new(),
+    };
+    let kicker_id = kicker_assignment.keys().next().copied();
+    assignments.extend(kicker_assignment);
+    assignments.extend(greedy_tactic_assignment(
+        tactics.greedy,
+        &mut robots,
+        world,
+        previous,
+    ));
     let robots: Vec<&Robot> = robots.into_values().collect();
-    assignments.extend(optimized_tactic_assignment(tactics.optimized, robots));
-    assignments
+    assignments.extend(optimized_tactic_assignment(
+        tactics.optimized,
+        robots,
+        world,
+        previous,
+    ));
+    (assignments, kicker_id)
 }
 
 impl Gameplay {
     pub fn tick(&mut self, world: &World) -> HashMap<usize, Trajectory> {
         // Update possession, ball model, etc.
+        // The config can be changed while we're running, e.g. to tune the strategy
+        // during a timeout. Only the latest reload matters
+        if let Some(config) = self.config_updates.try_iter().last() {
+            self.state.division_rules =
+                Division::from_config(&config.gameplay).rules(&config.rules);
+            self.state.rules = config.rules.clone();
+            self.state.strategy = config.gameplay.strategy.clone();
+            self.config = config;
+        }
+        let config = Arc::clone(&self.config);
+        let now = Instant::now();
+        self.state
+            .dribble_tracker
+            .update(&world.friendly_team.all_robots(), &world.ball, now);
+        self.update_restart_kicker(world);
+        self.state
+            .enemy_tracker
+            .update(world, &self.state.rules, now);
+        self.state.opponent_model.update(
+            world,
+            &self.state.enemy_tracker,
+            &config.gameplay.opponent_model_dir,
+            now,
+        );
+        let enemy_motion = self.state.opponent_model.stats().enemy_motion();
+        self.state.heatmaps.clear(
+            config.gameplay.heatmap_grid_spacing,
+            enemy_motion,
+            &self.state.rules,
+        );
+        let passer_id = world
+            .friendly_team
+            .players()
+            .into_iter()
+            .min_by(|a, b| {
+                (a.state.position - world.ball.position)
+                    .length()
+                    .total_cmp(&(b.state.position - world.ball.position).length())
+            })
+            .map(|r| r.id);
+        self.state.pass_optimizer.update(
+            world,
+            passer_id,
+            &enemy_motion,
+            &self.state.rules,
+            PASS_OPTIMIZER_TIME_BUDGET,
+        );
 
         // Update current play
-        self.update_current_play(world);
+        self.state.update_current_play(world, now);
 
         // Get tactics
         let requested_tactics = self.state.current_play.run(&world, &self.state);
 
         // Optimize/assign tactics
-        let unassigned_robots: HashMap<usize, &Robot> = world
-            .friendly_team
-            .all_robots()
-            .iter()
-            .map(|r| (r.id, *r))
-            .collect();
-        let robot_tactic_assignment =
-            assign_robots_to_tactics(requested_tactics, unassigned_robots);
+        let max_robots = self.state.division_rules.max_robots;
+        let (allowed, left_out) = allowed_robots(world, max_robots);
+        if left_out.len() != self.state.num_robots_left_out {
+            if !left_out.is_empty() {
+                println!(
+                    "Warning: {} robots on the field, but only {} are allowed",
+                    allowed.len() + left_out.len(),
+                    max_robots
+                );
+            }
+            self.state.num_robots_left_out = left_out.len();
+        }
+        let unassigned_robots: HashMap<usize, &Robot> =
+            allowed.into_iter().map(|r| (r.id, r)).collect();
+        let (robot_tactic_assignment, kicker_id) = assign_robots_to_tactics(
+            requested_tactics,
+            unassigned_robots,
+            world,
+            &self.state.active_tactics,
+        );
+        if world.game_state.our_restart() {
+            self.state.restart_kicker = kicker_id;
+        }
 
-        // Run tactics to get trajectories
-        let trajectories: HashMap<usize, Trajectory> = robot_tactic_assignment
-            .iter()
-            .map(|(id, t)| {
-                (
-                    *id,
-                    t.run(world.friendly_team.robot(&id).unwrap(), &world, &self.state),
-                )
+        // Keep each robot's tactic state while it's doing the same kind of thing,
+        // and start over when it's given something new
+        let mut active_tactics = std::mem::take(&mut self.state.active_tactics);
+        active_tactics.retain(|id, a| {
+            robot_tactic_assignment
+                .get(id)
+                .map_or(false, |t| a.is_same_kind(t))
+        });
+        for (id, t) in robot_tactic_assignment {
+            match active_tactics.get_mut(&id) {
+                Some(a) => a.set_tactic(t),
+                None => {
+                    active_tactics.insert(id, ActiveTactic::start(t));
+                }
+            }
+        }
+
+        // Run tactics to get trajectories, and make sure they follow the rules
+        let mut trajectories: HashMap<usize, Trajectory> = active_tactics
+            .iter_mut()
+            .map(|(id, a)| {
+                let robot = world.friendly_team.robot(&id).unwrap();
+                let constraints = MotionConstraints::from_game_state(
+                    world.friendly_team.goalie_id() == Some(*id),
+                    kicker_id == Some(*id),
+                    &world.game_state,
+                    &world.field,
+                    &world.ball,
+                    &self.state.rules,
+                );
+                let previous_status = a.status();
+                let trajectory = a.update(robot, &world, &self.state);
+                if a.status() != previous_status && a.status() != TacticStatus::Running {
+                    println!("Robot {} tactic {:?}", id, a.status());
+                }
+                (*id, constraints.apply(&robot.state, trajectory))
             })
             .collect();
+        self.state.active_tactics = active_tactics;
+        // Otherwise they'd keep following whatever they were last sent
+        for r in left_out {
+            trajectories.insert(r.id, stopping_trajectory(&r.state));
+        }
 
         // Return trajectories
         trajectories
     }
 
-    fn update_current_play(&mut self, world: &World) {
-        if !self.state.current_play.can_continue(&world.game_state) {
-            for p in Play::iter() {
-                if p.can_start(&world.game_state) {
-                    self.state.current_play = p;
-                    println!("Starting play: {}", self.state.current_play.to_string());
-                    return;
-                }
-            }
-            self.state.current_play = Play::Halt;
-            println!(
-                "No play can start. Falling back to : {}",
-                self.state.current_play.to_string()
-            );
+    // The robot that takes a restart may not touch the ball again until another robot has
+    fn update_restart_kicker(&mut self, world: &World) {
+        let kicker_id = match self.state.restart_kicker {
+            Some(id) => id,
+            None => return,
+        };
+        let touched_by_other_robot = world
+            .friendly_team
+            .all_robots()
+            .iter()
+            .filter(|r| r.id != kicker_id)
+            .chain(world.enemy_team.all_robots().iter())
+            .any(|r| has_ball(r, &world.ball));
+        if touched_by_other_robot || !world.game_state.playing() {
+            self.state.restart_kicker = None;
         }
     }
 }
@@ -169,16 +366,28 @@ impl Node for Gameplay {
             Err(_) => return Ok(()),
         };
         let trajectories = self.tick(&world);
-        self.output.trajectories.try_send(trajectories);
+        self.trajectories.try_send(trajectories);
+
+        if self.last_heatmap_publish_time.elapsed() > HEATMAP_PUBLISH_PERIOD {
+            self.heatmap_publisher.publish(&world, &self.state.heatmaps);
+            self.last_heatmap_publish_time = Instant::now();
+        }
 
         Ok(())
     }
 
     fn new(input: Self::Input, output: Self::Output, config: Arc<Mutex<Config>>) -> Self {
+        let config_updates = ConfigWatcher::new(CONFIG_FILEPATH).spawn(Arc::clone(&config));
+        let config = Arc::new(config.lock().unwrap().clone());
+        let state = State::new(&config);
         Self {
             input,
-            output,
-            state: State::new(),
+            trajectories: output.trajectories,
+            heatmap_publisher: HeatmapPublisher::new(output.heatmaps),
+            state,
+            config,
+            config_updates,
+            last_heatmap_publish_time: Instant::now(),
         }
     }
 
@@ -188,15 +397,284 @@ impl Node for Gameplay {
 }
 
 struct State {
-    enemy_max_speed: f64,
+    enemy_tracker: EnemyTracker,
+    opponent_model: OpponentModel,
     current_play: Play,
+    play_start_time: Instant,
+    rules: Rules,
+    division_rules: DivisionRules,
+    strategy: Strategy,
+    dribble_tracker: DribbleTracker,
+    restart_kicker: Option<usize>,
+    // So we only warn when it changes
+    num_robots_left_out: usize,
+    active_tactics: HashMap<usize, ActiveTactic>,
+    pass_optimizer: PassOptimizer,
+    heatmaps: HeatmapCache,
 }
 
 impl State {
-    pub fn new() -> Self {
+    pub fn new(config: &Config) -> Self {
         Self {
-            enemy_max_speed: 1.0, // Assume they can move somewhat
+            enemy_tracker: EnemyTracker::new(),
+            opponent_model: OpponentModel::new(),
             current_play: Play::Halt,
+            play_start_time: Instant::now(),
+            rules: config.rules.clone(),
+            division_rules: Division::from_config(&config.gameplay).rules(&config.rules),
+            strategy: config.gameplay.strategy.clone(),
+            dribble_tracker: DribbleTracker::new(),
+            restart_kicker: None,
+            num_robots_left_out: 0,
+            active_tactics: HashMap::new(),
+            pass_optimizer: PassOptimizer::new(),
+            heatmaps: HeatmapCache::new(config.gameplay.heatmap_grid_spacing, &config.rules),
+        }
+    }
+
+    // Plays run for a while before anything else can take over, unless they have to stop
+    fn update_current_play(&mut self, world: &World, now: Instant) {
+        let current_play = self.current_play;
+        let abort_reason = current_play.abort_reason(world);
+        if abort_reason.is_none()
+            && now.saturating_duration_since(self.play_start_time) < MIN_PLAY_RUN_TIME
+        {
+            return;
         }
+
+        let best_play = Play::iter()
+            .filter(|p| p.can_start(world))
+            .filter(|p| abort_reason.is_none() || *p != current_play)
+            .map(|p| {
+                let (mut score, reason) = p.score(world, self);
+                // Only switch to something clearly better than what we're doing
+                if p == current_play {
+                    score += PLAY_SWITCH_MARGIN;
+                }
+                (p, score, reason)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        let (play, reason) = match best_play {
+            Some((play, score, reason)) => (play, format!("score {:.2}, {}", score, reason)),
+            None => (Play::Halt, "no play can start".to_string()),
+        };
+        if play == current_play {
+            return;
+        }
+        if let Some(r) = abort_reason {
+            println!("Stopping play: {} ({})", current_play.to_string(), r);
+        }
+        println!("Starting play: {} ({})", play.to_string(), reason);
+        self.current_play = play;
+        self.play_start_time = now;
+    }
+
+    // How the tactic currently assigned to the robot is getting on, as of the last tick
+    pub fn tactic_status(&self, id: usize) -> Option<TacticStatus> {
+        self.active_tactics.get(&id).map(|a| a.status())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_config;
+    use crate::geom::{Angle, Point, Vector};
+    use crate::motion::KinematicState;
+    use crate::proto::ssl_gamecontroller::referee::Command;
+    use crate::world::{Ball, BallFriction, Field, GameState, Team, TeamInfo};
+
+    fn world_with_robots(positions: &[Point]) -> World {
+        let robots = positions
+            .iter()
+            .enumerate()
+            .map(|(id, p)| Robot {
+                id,
+                state: KinematicState {
+                    position: *p,
+                    orientation: Angle::zero(),
+                    velocity: Vector::new(),
+                    angular_velocity: Angle::zero(),
+                },
+            })
+            .collect();
+        let team_info = TeamInfo {
+            name: String::new(),
+            is_blue: true,
+            score: 0,
+            goalie_id: 0,
+            defending_positive_side: false,
+        };
+        World {
+            field: Field::ssl_div_b(),
+            ball: Ball {
+                position: Point::new(),
+                velocity: Vector::new(),
+                friction: BallFriction {
+                    sliding_deceleration: 3.0,
+                    rolling_deceleration: 0.5,
+                    rolling_speed: 2.0,
+                },
+            },
+            friendly_team: Team::new().with_robots(robots).build(),
+            enemy_team: Team::new(),
+            game_state: GameState::new(),
+            friendly_team_info: team_info.clone(),
+            enemy_team_info: team_info,
+        }
+    }
+
+    fn move_to(x: f64) -> Tactic {
+        Tactic::Move((Point { x, y: 1.0 }, Angle::zero()))
+    }
+
+    fn target_x(assignments: &HashMap<usize, Tactic>, id: usize) -> f64 {
+        match assignments.get(&id) {
+            Some(Tactic::Move((p, _))) => p.x,
+            _ => panic!("Robot {} wasn't given a move", id),
+        }
+    }
+
+    // The robots have drifted so that swapping targets is very slightly cheaper
+    fn noisy_world() -> World {
+        world_with_robots(&[Point { x: 0.55, y: 0.0 }, Point { x: 0.45, y: 0.0 }])
+    }
+
+    #[test]
+    fn assignment_swaps_without_history() {
+        let world = noisy_world();
+        let robots = world.friendly_team.all_robots();
+        let assignments = optimized_tactic_assignment(
+            vec![move_to(0.0), move_to(1.0)],
+            robots,
+            &world,
+            &HashMap::new(),
+        );
+        assert_eq!(target_x(&assignments, 0), 1.0);
+        assert_eq!(target_x(&assignments, 1), 0.0);
+    }
+
+    #[test]
+    fn assignment_is_stable_under_noise() {
+        let world = noisy_world();
+        let previous: HashMap<usize, ActiveTactic> = vec![
+            (0, ActiveTactic::start(move_to(0.0))),
+            (1, ActiveTactic::start(move_to(1.0))),
+        ]
+        .into_iter()
+        .collect();
+        for _ in 0..10 {
+            let robots = world.friendly_team.all_robots();
+            let assignments = optimized_tactic_assignment(
+                vec![move_to(0.0), move_to(1.0)],
+                robots,
+                &world,
+                &previous,
+            );
+            assert_eq!(target_x(&assignments, 0), 0.0);
+            assert_eq!(target_x(&assignments, 1), 1.0);
+        }
+    }
+
+    #[test]
+    fn least_important_tactics_dropped_when_short_of_robots() {
+        let world = world_with_robots(&[Point { x: 5.0, y: 1.0 }]);
+        let robots = world.friendly_team.all_robots();
+        let assignments = optimized_tactic_assignment(
+            vec![move_to(0.0), move_to(5.0)],
+            robots,
+            &world,
+            &HashMap::new(),
+        );
+        assert_eq!(target_x(&assignments, 0), 0.0);
+    }
+
+    // The game is running with the ball loose in their half
+    fn open_play_world() -> World {
+        let mut world = world_with_robots(&[]);
+        world.ball.position = Point { x: 3.0, y: 0.0 };
+        world.game_state.update_command(Command::ForceStart, true);
+        world
+    }
+
+    fn aggressive_state(play: Play, start_time: Instant) -> State {
+        let mut state = State::new(&load_config().unwrap());
+        state.strategy.aggressiveness = 1.0;
+        state.current_play = play;
+        state.play_start_time = start_time;
+        state
+    }
+
+    #[test]
+    fn play_is_kept_until_min_run_time() {
+        let world = open_play_world();
+        let start = Instant::now();
+        let mut state = aggressive_state(Play::Defense, start);
+        assert!(Play::Defense.abort_reason(&world).is_none());
+        state.update_current_play(&world, start + MIN_PLAY_RUN_TIME / 2);
+        assert_eq!(state.current_play, Play::Defense);
+        assert_eq!(state.play_start_time, start);
+    }
+
+    #[test]
+    fn better_play_takes_over_after_min_run_time() {
+        let world = open_play_world();
+        let start = Instant::now();
+        let mut state = aggressive_state(Play::Defense, start);
+        let later = start + MIN_PLAY_RUN_TIME + Duration::from_millis(100);
+        assert!(Play::Offense.score(&world, &state).0 > Play::Defense.score(&world, &state).0);
+        state.update_current_play(&world, later);
+        assert_eq!(state.current_play, Play::Offense);
+        assert_eq!(state.play_start_time, later);
+    }
+
+    #[test]
+    fn attacking_direction_follows_defending_side() {
+        // The ball is loose in their half while we defend the +x goal
+        let mut world = open_play_world();
+        world.ball.position = Point { x: -3.0, y: 0.0 };
+        world.field.defending_positive_side = true;
+        world.friendly_team_info.defending_positive_side = true;
+        let start = Instant::now();
+        let mut state = aggressive_state(Play::Defense, start);
+        // Neutral, so only where the ball is matters
+        state.strategy.aggressiveness = 0.5;
+        state.update_current_play(
+            &world,
+            start + MIN_PLAY_RUN_TIME + Duration::from_millis(100),
+        );
+        assert_eq!(state.current_play, Play::Offense);
+
+        world.ball.position = Point { x: 3.0, y: 0.0 };
+        assert!(Play::Defense.score(&world, &state).0 > Play::Offense.score(&world, &state).0);
+    }
+
+    #[test]
+    fn play_is_replaced_right_away_when_it_can_no_longer_run() {
+        let mut world = open_play_world();
+        world.game_state.update_command(Command::Stop, true);
+        let start = Instant::now();
+        let mut state = aggressive_state(Play::Offense, start);
+        assert!(!Play::Offense.can_start(&world));
+        assert!(Play::Offense.abort_reason(&world).is_some());
+        state.update_current_play(&world, start);
+        assert_eq!(state.current_play, Play::Stop);
+    }
+
+    #[test]
+    fn extra_robots_beyond_division_limit_are_left_out() {
+        let positions: Vec<Point> = (0..8)
+            .map(|i| Point {
+                x: i as f64,
+                y: 0.0,
+            })
+            .collect();
+        let mut world = world_with_robots(&positions);
+        world.friendly_team.set_goalie(Some(7));
+        let (allowed, left_out) = allowed_robots(&world, 6);
+        let ids: Vec<usize> = allowed.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![7, 0, 1, 2, 3, 4]);
+        let ids: Vec<usize> = left_out.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![5, 6]);
     }
 }
@@ -1,12 +1,23 @@
+mod ball_placement;
+mod defense;
+mod offense;
+mod restart;
+mod stop;
+
 use super::tactic::Tactic;
-use crate::gameplay::world::GameState;
+use crate::gameplay::evaluation::possession::{enemy_has_possession, friendly_has_possession};
 use crate::gameplay::world::World;
 use crate::gameplay::State;
-use crate::geom::{Angle, Point, Vector};
 use strum_macros::Display;
 use strum_macros::EnumIter;
 
 pub struct RequestedTactics {
+    // Always given to the goalie, if we have one
+    pub goalie: Option<Tactic>,
+    // Given to the robot taking a restart, which is then tracked so it doesn't
+    // touch the ball twice
+    pub kicker: Option<Tactic>,
+    // Most important first, in case there aren't enough robots for all of them
     pub greedy: Vec<Tactic>,
     pub optimized: Vec<Tactic>,
 }
@@ -14,39 +25,147 @@ pub struct RequestedTactics {
 impl RequestedTactics {
     pub fn new() -> RequestedTactics {
         RequestedTactics {
+            goalie: None,
+            kicker: None,
             greedy: vec![],
             optimized: vec![],
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, EnumIter, Display)]
+// Plays tied to a referee command are the only ones that can run at the time
+const GAME_STATE_PLAY_SCORE: f64 = 1.0;
+// How much possession, how far up the field the ball is, and the score difference
+// push us towards attacking rather than defending
+const BASE_PLAY_SCORE: f64 = 0.5;
+const POSSESSION_WEIGHT: f64 = 0.3;
+const FIELD_POSITION_WEIGHT: f64 = 0.2;
+const SCORE_DIFFERENCE_WEIGHT: f64 = 0.05;
+const MAX_SCORE_DIFFERENCE: f64 = 2.0;
+// How far the configured aggressiveness can push us either way
+const AGGRESSIVENESS_WEIGHT: f64 = 0.3;
+
+#[derive(Debug, Copy, Clone, PartialEq, EnumIter, Display)]
 pub enum Play {
     Halt,
     Stop,
+    OurBallPlacement,
+    TheirBallPlacement,
+    OurKickoff,
+    TheirKickoff,
+    OurFreeKick,
+    TheirFreeKick,
+    OurPenalty,
+    TheirPenalty,
+    Offense,
     Defense,
 }
 
+// Positive when we should be more aggressive, and negative when we should be more careful
+fn attacking_bias(world: &World, state: &State) -> (f64, String) {
+    let mut reasons: Vec<&str> = vec![];
+    let possession = if friendly_has_possession(world) {
+        reasons.push("we have the ball");
+        1.0
+    } else if enemy_has_possession(world) {
+        reasons.push("they have the ball");
+        -1.0
+    } else {
+        reasons.push("the ball is loose");
+        0.0
+    };
+    let field_position = (-world.field.friendly_side() * world.ball.position.x
+        / (world.field.x_length / 2.0))
+        .clamp(-1.0, 1.0);
+    reasons.push(if field_position > 0.0 {
+        "the ball is in their half"
+    } else {
+        "the ball is in our half"
+    });
+    // Take more risks when we're behind
+    let score_difference = (world.enemy_team_info.score as f64
+        - world.friendly_team_info.score as f64)
+        .clamp(-MAX_SCORE_DIFFERENCE, MAX_SCORE_DIFFERENCE);
+    if score_difference > 0.0 {
+        reasons.push("we're behind");
+    } else if score_difference < 0.0 {
+        reasons.push("we're ahead");
+    }
+    // Neutral at 0.5
+    let aggressiveness = 2.0 * state.strategy.aggressiveness.clamp(0.0, 1.0) - 1.0;
+    if aggressiveness > 0.0 {
+        reasons.push("we're playing aggressively");
+    } else if aggressiveness < 0.0 {
+        reasons.push("we're playing carefully");
+    }
+    let bias = POSSESSION_WEIGHT * possession
+        + FIELD_POSITION_WEIGHT * field_position
+        + SCORE_DIFFERENCE_WEIGHT * score_difference
+        + AGGRESSIVENESS_WEIGHT * aggressiveness;
+    (bias, reasons.join(", "))
+}
+
 impl Play {
-    pub fn can_start(&self, state: &GameState) -> bool {
+    // Whether the play makes any sense in the current situation
+    pub fn can_start(&self, world: &World) -> bool {
+        let state = &world.game_state;
         match self {
             Self::Halt => state.halted(),
             Self::Stop => state.stopped(),
+            Self::OurBallPlacement => state.our_ball_placement(),
+            Self::TheirBallPlacement => state.their_ball_placement(),
+            Self::OurKickoff => state.our_kickoff(),
+            Self::TheirKickoff => state.their_kickoff(),
+            Self::OurFreeKick => state.our_free_kick(),
+            Self::TheirFreeKick => state.their_free_kick(),
+            Self::OurPenalty => state.our_penalty(),
+            Self::TheirPenalty => state.their_penalty(),
+            Self::Offense => state.playing() && !enemy_has_possession(world),
             Self::Defense => state.playing(),
         }
     }
 
-    pub fn can_continue(&self, state: &GameState) -> bool {
+    // How good a choice the play is right now, and why. Only meaningful if it can start
+    pub fn score(&self, world: &World, state: &State) -> (f64, String) {
         match self {
-            Self::Halt => state.halted(),
-            Self::Stop => state.stopped(),
-            Self::Defense => state.playing(),
+            Self::Offense => {
+                let (bias, reason) = attacking_bias(world, state);
+                (BASE_PLAY_SCORE + bias, reason)
+            }
+            Self::Defense => {
+                let (bias, reason) = attacking_bias(world, state);
+                (BASE_PLAY_SCORE - bias, reason)
+            }
+            _ => (
+                GAME_STATE_PLAY_SCORE,
+                "required by the game state".to_string(),
+            ),
+        }
+    }
+
+    // Reasons the play has to stop right away, even if it hasn't run for long.
+    // Offense and defense only hand over once the other team has clearly won or lost
+    // the ball, so we don't switch back and forth while it's contested
+    pub fn abort_reason(&self, world: &World) -> Option<&'static str> {
+        let state = &world.game_state;
+        match self {
+            Self::Offense if state.playing() && enemy_has_possession(world) => {
+                Some("they have the ball")
+            }
+            Self::Defense if state.playing() && friendly_has_possession(world) => {
+                Some("we have the ball")
+            }
+            // Restarts end as soon as the ball is in play
+            _ if !self.can_start(world) => Some("the game state changed"),
+            _ => None,
         }
     }
 
     pub fn run(&self, world: &World, state: &State) -> RequestedTactics {
         match self {
             Self::Halt => RequestedTactics {
+                goalie: None,
+                kicker: None,
                 greedy: world
                     .friendly_team
                     .all_robots()
@@ -55,29 +174,17 @@ impl Play {
                     .collect(),
                 optimized: vec![],
             },
-            Self::Stop => {
-                let stop_positions: Vec<Point> = world
-                    .friendly_team
-                    .all_robots()
-                    .iter()
-                    .enumerate()
-                    .map(|(i, _)| {
-                        world.ball.position
-                            + Vector::from_angle(
-                                Angle::full() / (world.friendly_team.players().len() + 1) * i,
-                                1.0,
-                            )
-                    })
-                    .collect();
-                RequestedTactics {
-                    greedy: vec![],
-                    optimized: stop_positions
-                        .into_iter()
-                        .map(|p| Tactic::Move((p, Angle::zero())))
-                        .collect(),
-                }
-            }
-            Self::Defense => RequestedTactics::new(),
+            Self::Stop => stop::run(world, state),
+            Self::OurBallPlacement => ball_placement::ours(world, state),
+            Self::TheirBallPlacement => ball_placement::theirs(world, state),
+            Self::OurKickoff => restart::our_kickoff(world, state),
+            Self::TheirKickoff => restart::their_kickoff(world, state),
+            Self::OurFreeKick => restart::our_free_kick(world, state),
+            Self::TheirFreeKick => restart::their_free_kick(world, state),
+            Self::OurPenalty => restart::our_penalty(world, state),
+            Self::TheirPenalty => restart::their_penalty(world, state),
+            Self::Offense => offense::run(world, state),
+            Self::Defense => defense::run(world, state),
         }
     }
 }
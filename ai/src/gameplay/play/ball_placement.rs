@@ -0,0 +1,219 @@
+use super::defense;
+use super::offense::KICK_SPEED_MARGIN;
+use super::RequestedTactics;
+use crate::gameplay::tactic::Tactic;
+use crate::gameplay::world::{Ball, World};
+use crate::gameplay::State;
+use crate::geom::Point;
+use crate::motion::dribble::is_ball_moving;
+
+// Passing is faster than carrying the ball over long distances
+const MIN_PASS_PLACEMENT_DIST: f64 = 2.5;
+const NUM_KICK_SPEED_SEARCH_STEPS: usize = 20;
+
+// The kick speed that makes the ball come to rest at the target
+fn kick_speed_to_stop_at(ball: &Ball, target: &Point, max_speed: f64) -> f64 {
+    let direction = target - &ball.position;
+    let (mut low, mut high) = (0.0, max_speed);
+    for _ in 0..NUM_KICK_SPEED_SEARCH_STEPS {
+        let speed = (low + high) / 2.0;
+        let kicked_ball = Ball {
+            position: ball.position,
+            velocity: direction.norm(speed),
+            friction: ball.friction,
+        };
+        if (kicked_ball.stop_position() - ball.position).length() < direction.length() {
+            low = speed;
+        } else {
+            high = speed;
+        }
+    }
+    high
+}
+
+pub fn ours(world: &World, state: &State) -> RequestedTactics {
+    let target = match world.game_state.ball_placement_position() {
+        Some(p) => p,
+        None => return defense::run(world, state),
+    };
+    let ball = &world.ball;
+    let num_players = world.friendly_team.players().len();
+    let mut tactics = defense::run(world, state);
+
+    let should_pass = num_players >= 2
+        && (target - ball.position).length() > MIN_PASS_PLACEMENT_DIST
+        && !is_ball_moving(ball);
+    if should_pass {
+        let speed = kick_speed_to_stop_at(
+            ball,
+            &target,
+            state.rules.max_kick_speed - KICK_SPEED_MARGIN,
+        );
+        // Wait with the dribbler where the ball will stop
+        let receive_position = target
+            + (target - ball.position)
+                .norm(state.rules.robot_max_radius + state.rules.ball_max_radius);
+        tactics.optimized.truncate(num_players - 2);
+        tactics.optimized.insert(
+            0,
+            Tactic::Move((receive_position, (ball.position - target).orientation())),
+        );
+        tactics.kicker = Some(Tactic::Kick((target, speed)));
+    } else {
+        tactics.optimized.truncate(num_players.saturating_sub(1));
+        tactics.greedy = vec![Tactic::PlaceBall(target)];
+    }
+    tactics
+}
+
+// The rules keep us out of the way of the ball and its path to the target
+pub fn theirs(world: &World, state: &State) -> RequestedTactics {
+    defense::run_within_rules(world, state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_config;
+    use crate::gameplay::world::Robot;
+    use crate::geom::{Angle, Segment, Vector};
+    use crate::motion::KinematicState;
+    use crate::proto::ssl_gamecontroller::referee::Command;
+    use crate::world::{BallFriction, Field, GameState, Team, TeamInfo};
+
+    fn robot(id: usize, position: Point) -> Robot {
+        Robot {
+            id,
+            state: KinematicState {
+                position,
+                orientation: Angle::zero(),
+                velocity: Vector::new(),
+                angular_velocity: Angle::zero(),
+            },
+        }
+    }
+
+    fn world(command: Command, ball: Point, target: Point) -> World {
+        let team_info = TeamInfo {
+            name: String::new(),
+            is_blue: true,
+            score: 0,
+            goalie_id: 0,
+            defending_positive_side: false,
+        };
+        let friendly = (0..4)
+            .map(|id| {
+                robot(
+                    id,
+                    Point {
+                        x: -2.0,
+                        y: id as f64 - 1.5,
+                    },
+                )
+            })
+            .collect();
+        let mut game_state = GameState::new();
+        game_state.update_command(command, true);
+        game_state.update_ball_placement_position(Some(target));
+        World {
+            field: Field::ssl_div_b(),
+            ball: Ball {
+                position: ball,
+                velocity: Vector::new(),
+                friction: BallFriction {
+                    sliding_deceleration: 3.0,
+                    rolling_deceleration: 0.5,
+                    rolling_speed: 2.0,
+                },
+            },
+            friendly_team: Team::new().with_robots(friendly).with_goalie(0).build(),
+            enemy_team: Team::new()
+                .with_robots(vec![robot(0, Point { x: 1.0, y: 1.0 })])
+                .build(),
+            game_state,
+            friendly_team_info: team_info.clone(),
+            enemy_team_info: team_info,
+        }
+    }
+
+    #[test]
+    fn short_placement_carries_the_ball() {
+        let target = Point { x: 1.0, y: 0.5 };
+        let world = world(
+            Command::BallPlacementBlue,
+            Point { x: -0.5, y: -1.0 },
+            target,
+        );
+        let state = State::new(&load_config().unwrap());
+        let tactics = ours(&world, &state);
+        assert!(matches!(tactics.greedy.as_slice(), [Tactic::PlaceBall(p)] if *p == target));
+        assert!(tactics.kicker.is_none());
+        // Everyone else still defends
+        assert_eq!(tactics.optimized.len(), 2);
+    }
+
+    #[test]
+    fn long_placement_passes_then_places() {
+        let ball = Point { x: -1.0, y: -2.0 };
+        let target = Point { x: 2.0, y: 1.0 };
+        let world = world(Command::BallPlacementBlue, ball, target);
+        let state = State::new(&load_config().unwrap());
+        let tactics = ours(&world, &state);
+        assert!(tactics.greedy.is_empty());
+
+        // Kicked just hard enough to stop at the target
+        let speed = match tactics.kicker {
+            Some(Tactic::Kick((p, speed))) if p == target => speed,
+            _ => panic!("Expected the ball to be passed to the target"),
+        };
+        let kicked_ball = Ball {
+            position: ball,
+            velocity: (target - ball).norm(speed),
+            friction: world.ball.friction,
+        };
+        assert!((kicked_ball.stop_position() - target).length() < 0.05);
+
+        // Someone waits at the target, with the dribbler facing the ball
+        assert_eq!(tactics.optimized.len(), 2);
+        match tactics.optimized[0] {
+            Tactic::Move((p, orientation)) => {
+                let dist = state.rules.robot_max_radius + state.rules.ball_max_radius;
+                assert!(((p - target).length() - dist).abs() < 1e-6);
+                assert!((p - ball).length() > (target - ball).length());
+                assert!(
+                    (orientation.radians() - (ball - target).orientation().radians()).abs() < 1e-6
+                );
+            }
+            _ => panic!("Expected a robot to wait for the pass"),
+        }
+    }
+
+    #[test]
+    fn robots_stay_clear_of_enemy_placement() {
+        let state = State::new(&load_config().unwrap());
+        let clearance = state.rules.stop_min_dist_to_ball + state.rules.robot_max_radius;
+        // Carried straight past our ball defenders, and across the whole field
+        let placements = [
+            (Point { x: 1.0, y: 0.0 }, Point { x: -3.0, y: 0.0 }),
+            (Point { x: 3.0, y: -2.0 }, Point { x: -2.0, y: 2.0 }),
+        ];
+        for (ball, target) in placements {
+            let world = world(Command::BallPlacementYellow, ball, target);
+            let corridor = Segment::new(ball, target);
+            let tactics = theirs(&world, &state);
+            assert_eq!(tactics.optimized.len(), 3);
+            for t in &tactics.optimized {
+                match t {
+                    Tactic::Move((p, _)) => assert!(
+                        corridor.dist_to_point(p) >= clearance - 1e-6,
+                        "{:?} is in the way of the placement from {:?} to {:?}",
+                        p,
+                        ball,
+                        target
+                    ),
+                    _ => panic!("Expected robots to move"),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,378 @@
+use super::stop::legal_position;
+use super::RequestedTactics;
+use crate::gameplay::evaluation::enemy_prediction::{
+    predict_ball_destinations, BallDestinationKind,
+};
+use crate::gameplay::evaluation::threats::EnemyRole;
+use crate::gameplay::tactic::Tactic;
+use crate::gameplay::world::{Robot, World};
+use crate::gameplay::State;
+use crate::geom::{Angle, Point, Rectangle, Vector};
+
+// Two defenders side by side can cover the ball's whole shot cone at the defense area
+const MAX_BALL_DEFENDERS: usize = 2;
+// How far outside the defense area defenders stand, on top of the robot radius
+const DEFENDER_MARGIN: f64 = 0.05;
+// How far from the enemy a shadowing robot stands, between them and the ball
+const SHADOW_DIST: f64 = 0.5;
+// Where robots go when there are no enemies left to cover
+const SUPPORT_DIST_FROM_BALL: f64 = 1.5;
+const SUPPORT_SPACING: f64 = 0.5;
+const NUM_BOUNDARY_SEARCH_STEPS: usize = 20;
+const NUM_SPREAD_STEPS: usize = 10;
+// Anything closer than this is treated as sitting on the goal center
+const MIN_DIST_TO_GOAL: f64 = 1.0e-6;
+// Receivers at least this likely to be passed to are covered before anyone else
+const MIN_PREDICTED_PASS_LIKELIHOOD: f64 = 0.2;
+
+// Finds where the segment from outside to inside crosses into the area
+fn boundary_crossing(area: &Rectangle, outside: Point, inside: Point) -> Point {
+    let (mut outside, mut inside) = (outside, inside);
+    for _ in 0..NUM_BOUNDARY_SEARCH_STEPS {
+        let mid = outside + (inside - outside) / 2.0;
+        if area.contains(&mid) {
+            inside = mid;
+        } else {
+            outside = mid;
+        }
+    }
+    outside
+}
+
+fn facing(from: &Point, to: &Point) -> Angle {
+    (to - from).orientation()
+}
+
+// Points from p to our goal, or straight out of the goal if p is already on it
+fn toward_goal(world: &World, p: &Point) -> Vector {
+    let goal = world.field.friendly_goal_center();
+    let v = goal - p;
+    if v.length() > MIN_DIST_TO_GOAL {
+        v
+    } else {
+        goal - Point::new()
+    }
+}
+
+// Positions just outside the defense area that block shots from the threat
+pub fn block_positions(
+    world: &World,
+    threat: &Point,
+    num_robots: usize,
+    robot_radius: f64,
+) -> Vec<Point> {
+    let goal = world.field.friendly_goal_center();
+    let area = world
+        .field
+        .friendly_defense_area()
+        .expand(robot_radius + DEFENDER_MARGIN);
+    let center = if area.contains(threat) {
+        area.closest_point_on_boundary(threat)
+    } else {
+        boundary_crossing(&area, *threat, goal)
+    };
+    let sideways = toward_goal(world, threat).perpendicular().norm(1.0);
+    let offsets: Vec<f64> = match num_robots {
+        0 => vec![],
+        1 => vec![0.0],
+        _ => vec![-1.0, 1.0],
+    };
+    let place = |spread: f64| -> Vec<Point> {
+        offsets
+            .iter()
+            .map(|o| {
+                let p = center + sideways * (o * spread);
+                if area.contains(&p) {
+                    area.closest_point_on_boundary(&p)
+                } else {
+                    p
+                }
+            })
+            .collect()
+    };
+
+    // Pushing a robot back out of the area near a corner squeezes the pair together,
+    // so spread them further until they fit side by side
+    let mut spread = robot_radius;
+    let mut positions = place(spread);
+    for _ in 0..NUM_SPREAD_STEPS {
+        let gap = match positions.as_slice() {
+            [a, b] => (a - b).length(),
+            _ => break,
+        };
+        if gap >= 2.0 * robot_radius {
+            break;
+        }
+        spread += 2.0 * robot_radius - gap;
+        positions = place(spread);
+    }
+    positions
+}
+
+fn shadow_position(enemy: &Robot, world: &World) -> Point {
+    let ball = world.ball.position;
+    let enemy_to_ball = ball - enemy.state.position;
+    if enemy_to_ball.length() > SHADOW_DIST {
+        enemy.state.position + enemy_to_ball.norm(SHADOW_DIST)
+    } else {
+        let enemy_to_goal = world.field.friendly_goal_center() - enemy.state.position;
+        enemy.state.position + enemy_to_goal.norm(SHADOW_DIST)
+    }
+}
+
+// Enemies that could receive a pass or take a shot, most dangerous first. Whoever the
+// ball carrier is most likely to pass to comes first, so we're there before the ball is.
+// The ball carrier is left to the ball defenders
+fn ranked_threats<'a>(world: &'a World, state: &State) -> Vec<&'a Robot> {
+    let mut ids: Vec<usize> = predict_ball_destinations(
+        world,
+        &state.enemy_tracker,
+        state.opponent_model.stats(),
+        &state.rules,
+    )
+    .into_iter()
+    .filter(|d| d.likelihood > MIN_PREDICTED_PASS_LIKELIHOOD)
+    .filter_map(|d| match d.kind {
+        BallDestinationKind::Pass(id) => Some(id),
+        BallDestinationKind::Shot => None,
+    })
+    .collect();
+    for t in state.enemy_tracker.threats() {
+        if t.role != EnemyRole::Goalie && t.role != EnemyRole::BallCarrier && !ids.contains(&t.id) {
+            ids.push(t.id);
+        }
+    }
+    ids.iter()
+        .filter_map(|id| world.enemy_team.robot(id))
+        .collect()
+}
+
+pub fn run(world: &World, state: &State) -> RequestedTactics {
+    let robot_radius = state.rules.robot_max_radius;
+    let ball = world.ball.position;
+    let num_players = world.friendly_team.players().len();
+    let max_defenders = state.strategy.defense_max_defenders as usize;
+    let num_defenders = num_players.div_ceil(2).min(max_defenders);
+    let num_ball_defenders = num_defenders.min(MAX_BALL_DEFENDERS);
+
+    let mut tactics: Vec<Tactic> = block_positions(world, &ball, num_ball_defenders, robot_radius)
+        .into_iter()
+        .map(|p| Tactic::Move((p, facing(&p, &ball))))
+        .collect();
+
+    let mut threats = ranked_threats(world, state).into_iter();
+    for _ in num_ball_defenders..num_defenders {
+        let threat = match threats.next() {
+            Some(t) => t.state.position,
+            None => break,
+        };
+        for p in block_positions(world, &threat, 1, robot_radius) {
+            tactics.push(Tactic::Move((p, facing(&p, &threat))));
+        }
+    }
+
+    while tactics.len() < num_players {
+        let position = match threats.next() {
+            Some(enemy) => shadow_position(enemy, world),
+            None => {
+                // Nobody left to mark, so help cover the space in front of our goal
+                let toward_goal = toward_goal(world, &ball);
+                let spread = (tactics.len() as f64 - num_players as f64 / 2.0) * SUPPORT_SPACING;
+                ball + toward_goal.norm(SUPPORT_DIST_FROM_BALL)
+                    + toward_goal.perpendicular().norm(spread)
+            }
+        };
+        tactics.push(Tactic::Move((position, facing(&position, &ball))));
+    }
+
+    RequestedTactics {
+        goalie: Some(Tactic::Goalie),
+        kicker: None,
+        greedy: vec![],
+        optimized: tactics,
+    }
+}
+
+// Defends from spots the rules let us stand in, for when the enemy restarts and we have to
+// keep out of their way
+pub fn run_within_rules(world: &World, state: &State) -> RequestedTactics {
+    let mut tactics = run(world, state);
+    for t in tactics.optimized.iter_mut() {
+        if let Tactic::Move((p, _)) = t {
+            *p = legal_position(world, state, p);
+        }
+    }
+    tactics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_config;
+    use crate::geom::Segment;
+    use crate::motion::KinematicState;
+    use crate::world::{Ball, BallFriction, Field, GameState, Team, TeamInfo};
+    use std::time::Instant;
+
+    fn robot(id: usize, position: Point) -> Robot {
+        Robot {
+            id,
+            state: KinematicState {
+                position,
+                orientation: Angle::zero(),
+                velocity: Vector::new(),
+                angular_velocity: Angle::zero(),
+            },
+        }
+    }
+
+    fn world(num_players: usize, enemy: Vec<Robot>, ball: Point) -> World {
+        let team_info = TeamInfo {
+            name: String::new(),
+            is_blue: true,
+            score: 0,
+            goalie_id: 0,
+            defending_positive_side: false,
+        };
+        let friendly = (0..=num_players)
+            .map(|id| {
+                robot(
+                    id,
+                    Point {
+                        x: -4.0,
+                        y: id as f64 * 0.3,
+                    },
+                )
+            })
+            .collect();
+        World {
+            field: Field::ssl_div_b(),
+            ball: Ball {
+                position: ball,
+                velocity: Vector::new(),
+                friction: BallFriction {
+                    sliding_deceleration: 3.0,
+                    rolling_deceleration: 0.5,
+                    rolling_speed: 2.0,
+                },
+            },
+            friendly_team: Team::new().with_robots(friendly).with_goalie(0).build(),
+            enemy_team: Team::new().with_robots(enemy).build(),
+            game_state: GameState::new(),
+            friendly_team_info: team_info.clone(),
+            enemy_team_info: team_info,
+        }
+    }
+
+    fn state(world: &World) -> State {
+        let mut state = State::new(&load_config().unwrap());
+        state
+            .enemy_tracker
+            .update(world, &state.rules, Instant::now());
+        state
+    }
+
+    fn positions(tactics: &RequestedTactics) -> Vec<Point> {
+        tactics
+            .optimized
+            .iter()
+            .map(|t| match t {
+                Tactic::Move((p, _)) => *p,
+                _ => panic!("Expected defenders to move"),
+            })
+            .collect()
+    }
+
+    // Blocking from just outside the defense area, within max_dist of the line to our goal
+    fn assert_blocks(world: &World, p: &Point, threat: &Point, max_dist: f64) {
+        let goal = world.field.friendly_goal_center();
+        assert!(!world.field.friendly_defense_area().contains(p));
+        assert!(Segment::new(*threat, goal).dist_to_point(p) <= max_dist + 1e-6);
+    }
+
+    fn enemies() -> Vec<Robot> {
+        vec![
+            // Has the ball
+            robot(0, Point { x: 0.0, y: 0.0 }),
+            robot(1, Point { x: -2.0, y: 2.0 }),
+            robot(2, Point { x: -2.5, y: -1.5 }),
+            robot(3, Point { x: 1.0, y: 2.5 }),
+        ]
+    }
+
+    #[test]
+    fn goalie_is_always_requested() {
+        for num_players in [1, 3, 6] {
+            let world = world(num_players, enemies(), Point { x: 0.2, y: 0.0 });
+            let tactics = run(&world, &state(&world));
+            assert!(matches!(tactics.goalie, Some(Tactic::Goalie)));
+            assert_eq!(tactics.optimized.len(), num_players);
+        }
+    }
+
+    #[test]
+    fn single_robot_blocks_the_ball() {
+        let ball = Point { x: 0.2, y: 0.0 };
+        let world = world(1, enemies(), ball);
+        let state = state(&world);
+        let positions = positions(&run(&world, &state));
+        assert_eq!(positions.len(), 1);
+        assert_blocks(&world, &positions[0], &ball, 0.0);
+    }
+
+    #[test]
+    fn two_robots_block_the_ball_and_one_shadows() {
+        let ball = Point { x: 0.2, y: 1.0 };
+        let world = world(3, enemies(), ball);
+        let state = state(&world);
+        let radius = state.rules.robot_max_radius;
+        let positions = positions(&run(&world, &state));
+        assert_eq!(positions.len(), 3);
+        assert_blocks(&world, &positions[0], &ball, 2.0 * radius);
+        assert_blocks(&world, &positions[1], &ball, 2.0 * radius);
+        assert!((positions[0] - positions[1]).length() >= 2.0 * radius - 1e-3);
+
+        // Whoever is most dangerous gets shadowed, from the side the ball is on
+        let top = ranked_threats(&world, &state)[0];
+        assert_ne!(top.id, 0);
+        let shadow = positions[2];
+        assert!(((shadow - top.state.position).length() - SHADOW_DIST).abs() < 1e-6);
+        assert!((ball - shadow).length() < (ball - top.state.position).length());
+    }
+
+    #[test]
+    fn full_team_blocks_the_top_threat_and_shadows_the_rest() {
+        let ball = Point { x: 0.2, y: 0.0 };
+        let world = world(6, enemies(), ball);
+        let state = state(&world);
+        let radius = state.rules.robot_max_radius;
+        let positions = positions(&run(&world, &state));
+        assert_eq!(positions.len(), 6);
+        assert_blocks(&world, &positions[0], &ball, 2.0 * radius);
+        assert_blocks(&world, &positions[1], &ball, 2.0 * radius);
+
+        let ranked = ranked_threats(&world, &state);
+        assert_eq!(ranked.len(), 3);
+        assert_blocks(&world, &positions[2], &ranked[0].state.position, 0.0);
+        for (p, enemy) in positions[3..5].iter().zip(&ranked[1..]) {
+            assert!(((*p - enemy.state.position).length() - SHADOW_DIST).abs() < 1e-6);
+        }
+        // Nobody left to mark, so the last robot covers the space in front of goal
+        let goal = world.field.friendly_goal_center();
+        assert!((positions[5] - goal).length() < (ball - goal).length());
+    }
+
+    #[test]
+    fn ball_or_threat_on_goal_center_still_gives_positions() {
+        let goal = Field::ssl_div_b().friendly_goal_center();
+        let enemies = vec![robot(0, Point { x: 2.0, y: 0.0 }), robot(1, goal)];
+        let world = world(6, enemies, goal);
+        let state = state(&world);
+        for p in positions(&run(&world, &state)) {
+            assert!(p.x.is_finite() && p.y.is_finite());
+        }
+        for p in block_positions(&world, &goal, 2, state.rules.robot_max_radius) {
+            assert!(p.x.is_finite() && p.y.is_finite());
+        }
+    }
+}
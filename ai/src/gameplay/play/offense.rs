@@ -0,0 +1,394 @@
+use super::defense::block_positions;
+use super::RequestedTactics;
+use crate::gameplay::evaluation::best_shot::best_shot_on_goal;
+use crate::gameplay::evaluation::passing::{score_pass, Pass};
+use crate::gameplay::tactic::Tactic;
+use crate::gameplay::world::{Robot, World};
+use crate::gameplay::State;
+use crate::geom::Point;
+
+// Stay safely under the max kick speed, since kicks aren't perfectly consistent
+pub const KICK_SPEED_MARGIN: f64 = 0.5;
+pub const PASS_SPEED: f64 = 4.0;
+const PASS_TIME_OFFSET: f64 = 0.15;
+const MIN_PASS_DIST: f64 = 1.0;
+// Receivers are spread out so a single enemy can't cover several of them
+const MIN_RECEIVER_SEPARATION: f64 = 1.0;
+// How far to dribble towards the goal when there's nothing better to do
+const DRIBBLE_DIST: f64 = 1.0;
+// With fewer players than this everyone attacks, no matter the strategy
+const MIN_PLAYERS_FOR_DEFENDER: usize = 4;
+// Where supporters wait until the pass optimizer has found enough places for them
+const FALLBACK_SUPPORT_DIST: f64 = 1.5;
+const FALLBACK_EDGE_MARGIN: f64 = 0.5;
+// A ball moving at least this fast towards a teammate is a pass for them to receive
+const PASS_IN_FLIGHT_MIN_SPEED: f64 = 1.0;
+const RECEIVE_MAX_DIST_FROM_PATH: f64 = 0.5;
+
+pub fn pass_to(world: &World, target: &Point) -> Pass {
+    Pass {
+        start: world.ball.position,
+        end: *target,
+        speed: PASS_SPEED,
+        time_offset: PASS_TIME_OFFSET,
+        friction: world.ball.friction,
+    }
+}
+
+// The best places for supporting attackers to receive a pass, best first
+pub fn receive_positions(world: &World, state: &State, num: usize) -> Vec<Point> {
+    // Supporters shouldn't be pulled back too far from the attack
+    let side = world.field.friendly_side();
+    let max_dist_back = world.field.x_length / 4.0;
+    let mut positions: Vec<Point> = vec![];
+    for (pass, _) in state.pass_optimizer.best_passes(usize::MAX) {
+        if positions.len() >= num {
+            break;
+        }
+        let p = pass.end;
+        if p.x * side <= max_dist_back
+            && (p - world.ball.position).length() > MIN_PASS_DIST
+            && positions
+                .iter()
+                .all(|q| (p - q).length() > MIN_RECEIVER_SEPARATION)
+        {
+            positions.push(p);
+        }
+    }
+    positions
+}
+
+// Spread across the field a little ahead of the ball, outside the enemy defense area
+fn fallback_receive_positions(world: &World, num: usize) -> Vec<Point> {
+    let field = &world.field;
+    let forward = -field.friendly_side();
+    let max_x = field.x_length / 2.0 - field.defense_x_length - FALLBACK_EDGE_MARGIN;
+    let x = (world.ball.position.x + forward * FALLBACK_SUPPORT_DIST).clamp(-max_x, max_x);
+    let max_y = field.y_length / 2.0 - FALLBACK_EDGE_MARGIN;
+    (0..num)
+        .map(|i| Point {
+            x,
+            y: -max_y + 2.0 * max_y * (i as f64 + 0.5) / num as f64,
+        })
+        .collect()
+}
+
+// Kicks the ball at the goal or a teammate if either is good enough
+pub fn shot_or_pass(
+    world: &World,
+    state: &State,
+    kicker: &Robot,
+    receivers: &[Robot],
+    enemies: &[Robot],
+) -> Option<Tactic> {
+    let ball = world.ball.position;
+    let kick_speed = state.rules.max_kick_speed - KICK_SPEED_MARGIN;
+    if let Some(shot) = best_shot_on_goal(world, &ball, Some(kicker.id), &state.rules) {
+        if shot.score_probability > state.strategy.min_shot_score_probability {
+            return Some(Tactic::Kick((shot.target, kick_speed)));
+        }
+    }
+
+    let enemy_motion = state.opponent_model.stats().enemy_motion();
+    let best_pass = receivers
+        .iter()
+        .filter(|r| (r.state.position - ball).length() > MIN_PASS_DIST)
+        .map(|r| {
+            let pass = pass_to(world, &r.state.position);
+            let score = score_pass(
+                &pass,
+                &world.field,
+                receivers,
+                enemies,
+                &enemy_motion,
+                &state.rules,
+            );
+            (pass, score)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    match best_pass {
+        Some((pass, score)) if score > state.strategy.min_pass_score => {
+            Some(Tactic::Kick((pass.end, pass.speed.min(kick_speed))))
+        }
+        _ => None,
+    }
+}
+
+// Decides whether the ball carrier should shoot, pass or dribble
+fn ball_carrier_tactic(
+    world: &World,
+    state: &State,
+    carrier: &Robot,
+    receivers: &[Robot],
+    enemies: &[Robot],
+) -> Tactic {
+    if let Some(t) = shot_or_pass(world, state, carrier, receivers, enemies) {
+        return t;
+    }
+    let ball = world.ball.position;
+    let toward_goal = world.field.enemy_goal_center() - ball;
+    Tactic::Dribble((
+        ball + toward_goal.norm(DRIBBLE_DIST),
+        toward_goal.orientation(),
+    ))
+}
+
+pub fn closest_player_to_ball<'a>(world: &'a World, exclude: Option<usize>) -> Option<&'a Robot> {
+    let ball = world.ball.position;
+    world
+        .friendly_team
+        .players()
+        .into_iter()
+        .filter(|r| Some(r.id) != exclude)
+        .min_by(|a, b| {
+            (a.state.position - ball)
+                .length()
+                .total_cmp(&(b.state.position - ball).length())
+        })
+}
+
+// Everyone except the ball carrier and the goalie. Some of them
+// get open for a pass, and one stays back if there are enough robots
+pub fn supporting_tactics(world: &World, state: &State, num_robots: usize) -> Vec<Tactic> {
+    let ball = world.ball.position;
+    let mut tactics: Vec<Tactic> = vec![];
+    if num_robots + 1 >= MIN_PLAYERS_FOR_DEFENDER {
+        let num_defenders = state.strategy.offense_num_defenders as usize;
+        for p in block_positions(world, &ball, num_defenders, state.rules.robot_max_radius) {
+            tactics.push(Tactic::Move((p, (ball - p).orientation())));
+        }
+    }
+    let num_supporters = num_robots.saturating_sub(tactics.len());
+    let mut positions = receive_positions(world, state, num_supporters);
+    // Every robot needs something to do, even before the pass optimizer has caught up
+    positions.extend(fallback_receive_positions(
+        world,
+        num_supporters - positions.len(),
+    ));
+    for p in positions {
+        tactics.push(Tactic::Move((p, (ball - p).orientation())));
+    }
+    tactics
+}
+
+pub fn teammates_and_enemies(world: &World, kicker_id: usize) -> (Vec<Robot>, Vec<Robot>) {
+    let receivers: Vec<Robot> = world
+        .friendly_team
+        .players()
+        .into_iter()
+        .filter(|r| r.id != kicker_id)
+        .cloned()
+        .collect();
+    let enemies: Vec<Robot> = world.enemy_team.all_robots().into_iter().cloned().collect();
+    (receivers, enemies)
+}
+
+// If the ball is on its way to a teammate, the first one in its path catches it
+fn receive_tactic(world: &World) -> Option<Tactic> {
+    let ball = &world.ball;
+    let speed = ball.velocity.length();
+    if speed < PASS_IN_FLIGHT_MIN_SPEED {
+        return None;
+    }
+    world
+        .friendly_team
+        .players()
+        .into_iter()
+        .filter_map(|r| {
+            let time = ball.time_to_reach(&r.state.position)?;
+            let along = (r.state.position - ball.position).dot(&ball.velocity) / speed;
+            let on_path = ball.position + ball.velocity.norm(along);
+            if (r.state.position - on_path).length() < RECEIVE_MAX_DIST_FROM_PATH {
+                Some((time, on_path))
+            } else {
+                None
+            }
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, end)| {
+            Tactic::Receive(Pass {
+                start: ball.position,
+                end,
+                speed,
+                time_offset: 0.0,
+                friction: ball.friction,
+            })
+        })
+}
+
+pub fn run(world: &World, state: &State) -> RequestedTactics {
+    let goalie = Some(Tactic::Goalie);
+    if let Some(receive) = receive_tactic(world) {
+        let num_supporters = world.friendly_team.players().len().saturating_sub(1);
+        return RequestedTactics {
+            goalie,
+            kicker: None,
+            greedy: vec![receive],
+            optimized: supporting_tactics(world, state, num_supporters),
+        };
+    }
+    // The robot that took the last restart isn't allowed to touch the ball again yet
+    let carrier = match closest_player_to_ball(world, state.restart_kicker) {
+        Some(r) => r,
+        None => {
+            return RequestedTactics {
+                goalie,
+                kicker: None,
+                greedy: vec![],
+                optimized: vec![],
+            }
+        }
+    };
+
+    let (receivers, enemies) = teammates_and_enemies(world, carrier.id);
+    RequestedTactics {
+        goalie,
+        kicker: None,
+        greedy: vec![ball_carrier_tactic(
+            world, state, carrier, &receivers, &enemies,
+        )],
+        optimized: supporting_tactics(world, state, receivers.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_config;
+    use crate::geom::{Angle, Vector};
+    use crate::motion::KinematicState;
+    use crate::world::{Ball, BallFriction, Field, GameState, Team, TeamInfo};
+
+    fn robot(id: usize, position: Point) -> Robot {
+        Robot {
+            id,
+            state: KinematicState {
+                position,
+                orientation: Angle::zero(),
+                velocity: Vector::new(),
+                angular_velocity: Angle::zero(),
+            },
+        }
+    }
+
+    fn world(friendly: Vec<Robot>, enemy: Vec<Robot>, ball: Point) -> World {
+        let team_info = TeamInfo {
+            name: String::new(),
+            is_blue: true,
+            score: 0,
+            goalie_id: 0,
+            defending_positive_side: false,
+        };
+        World {
+            field: Field::ssl_div_b(),
+            ball: Ball {
+                position: ball,
+                velocity: Vector::new(),
+                friction: BallFriction {
+                    sliding_deceleration: 3.0,
+                    rolling_deceleration: 0.5,
+                    rolling_speed: 2.0,
+                },
+            },
+            friendly_team: Team::new().with_robots(friendly).with_goalie(0).build(),
+            enemy_team: Team::new().with_robots(enemy).build(),
+            game_state: GameState::new(),
+            friendly_team_info: team_info.clone(),
+            enemy_team_info: team_info,
+        }
+    }
+
+    #[test]
+    fn carrier_shoots_at_open_goal() {
+        let friendly = vec![
+            robot(0, Point { x: -4.3, y: 0.0 }),
+            robot(1, Point { x: 2.9, y: 0.0 }),
+        ];
+        let world = world(friendly, vec![], Point { x: 3.0, y: 0.0 });
+        let state = State::new(&load_config().unwrap());
+        let tactics = run(&world, &state);
+        match tactics.greedy.as_slice() {
+            [Tactic::Kick((target, _))] => {
+                assert!((target - &world.field.enemy_goal_center()).length() < 0.5)
+            }
+            _ => panic!("Expected the carrier to shoot"),
+        }
+    }
+
+    #[test]
+    fn every_supporter_gets_a_position_before_passes_are_found() {
+        let friendly = (0..6)
+            .map(|id| {
+                robot(
+                    id,
+                    Point {
+                        x: -1.0,
+                        y: id as f64 - 2.5,
+                    },
+                )
+            })
+            .collect();
+        let world = world(friendly, vec![], Point { x: 0.0, y: 0.0 });
+        // Nothing has been optimized yet
+        let state = State::new(&load_config().unwrap());
+        let tactics = supporting_tactics(&world, &state, 5);
+        assert_eq!(tactics.len(), 5);
+        for t in &tactics {
+            match t {
+                Tactic::Move((p, _)) => assert!(world.field.touch_lines().contains(p)),
+                _ => panic!("Expected supporters to move"),
+            }
+        }
+    }
+
+    #[test]
+    fn teammate_in_path_receives_pass() {
+        let friendly = vec![
+            robot(0, Point { x: -4.3, y: 0.0 }),
+            robot(1, Point { x: -1.0, y: 0.0 }),
+            robot(2, Point { x: 1.0, y: 2.1 }),
+        ];
+        let mut world = world(friendly, vec![], Point { x: -0.8, y: 0.1 });
+        world.ball.velocity = Vector {
+            x: 1.8,
+            y: 2.0 - 0.1,
+        }
+        .norm(3.0);
+        let state = State::new(&load_config().unwrap());
+        let tactics = run(&world, &state);
+        match tactics.greedy.as_slice() {
+            [Tactic::Receive(pass)] => {
+                assert!((pass.end - Point { x: 1.0, y: 2.1 }).length() < 0.2)
+            }
+            _ => panic!("Expected the teammate to receive the pass"),
+        }
+    }
+
+    #[test]
+    fn carrier_passes_when_shot_is_blocked() {
+        let friendly = vec![
+            robot(0, Point { x: -4.3, y: 0.0 }),
+            robot(1, Point { x: 0.9, y: 0.0 }),
+            robot(2, Point { x: 1.0, y: 2.0 }),
+        ];
+        // A wall of enemies in front of the goal
+        let enemy = (0..9)
+            .map(|i| {
+                robot(
+                    i,
+                    Point {
+                        x: 3.5,
+                        y: -0.8 + 0.2 * i as f64,
+                    },
+                )
+            })
+            .collect();
+        let world = world(friendly, enemy, Point { x: 1.0, y: 0.0 });
+        let state = State::new(&load_config().unwrap());
+        let tactics = run(&world, &state);
+        match tactics.greedy.as_slice() {
+            [Tactic::Kick((target, _))] => assert!((target.y - 2.0).abs() < 1.0e-6),
+            _ => panic!("Expected the carrier to pass"),
+        }
+    }
+}
@@ -0,0 +1,484 @@
+use super::defense;
+use super::offense::{
+    closest_player_to_ball, pass_to, receive_positions, shot_or_pass, supporting_tactics,
+    teammates_and_enemies, KICK_SPEED_MARGIN, PASS_SPEED,
+};
+use super::RequestedTactics;
+use crate::gameplay::evaluation::best_shot::best_shot_on_goal;
+use crate::gameplay::evaluation::passing::score_pass;
+use crate::gameplay::tactic::Tactic;
+use crate::gameplay::world::World;
+use crate::gameplay::State;
+use crate::geom::{Point, Vector};
+use crate::world::PenaltyProcedure;
+
+// Where the kicker waits until it's allowed to touch the ball
+const KICKER_STAGING_DIST: f64 = 0.25;
+// Extra distance kept from lines we aren't allowed to cross
+const POSITION_MARGIN: f64 = 0.1;
+const FIELD_EDGE_MARGIN: f64 = 0.3;
+// How far from the ball the kickoff receivers wait, to the side
+const KICKOFF_RECEIVER_DIST_Y: f64 = 1.5;
+// How far to dribble at a time during a penalty, when the division allows it
+const PENALTY_DRIBBLE_DIST: f64 = 0.5;
+
+fn kick_or_wait(world: &World, target: &Point, speed: f64) -> Tactic {
+    if world.game_state.can_manipulate_ball() {
+        Tactic::Kick((*target, speed))
+    } else {
+        let orientation = (target - &world.ball.position).orientation();
+        let staging_position =
+            world.ball.position + Vector::from_angle(orientation, -KICKER_STAGING_DIST);
+        Tactic::Move((staging_position, orientation))
+    }
+}
+
+// Robots spread evenly along a line across the field, facing the ball
+fn line_across_field(world: &World, x: f64, num_robots: usize) -> Vec<Tactic> {
+    let max_y = world.field.y_length / 2.0 - FIELD_EDGE_MARGIN;
+    (0..num_robots)
+        .map(|i| {
+            let y = -max_y + 2.0 * max_y * (i as f64 + 0.5) / num_robots as f64;
+            let p = Point { x, y };
+            Tactic::Move((p, (world.ball.position - p).orientation()))
+        })
+        .collect()
+}
+
+pub fn our_kickoff(world: &World, state: &State) -> RequestedTactics {
+    let robot_radius = state.rules.robot_max_radius;
+    let ball = world.ball.position;
+    let side = world.field.friendly_side();
+    let num_players = world.friendly_team.players().len();
+    // Two receivers wait on either side of the ball. The kicker passes to whichever
+    // is more open, so we aren't predictable
+    let receiver_positions: Vec<Point> = [-1.0, 1.0]
+        .iter()
+        .map(|offset| Point {
+            x: side * (robot_radius + POSITION_MARGIN),
+            y: ball.y + offset * KICKOFF_RECEIVER_DIST_Y,
+        })
+        .take(num_players.saturating_sub(1))
+        .collect();
+
+    let (receivers, enemies) = match closest_player_to_ball(world, None) {
+        Some(kicker) => teammates_and_enemies(world, kicker.id),
+        None => (vec![], vec![]),
+    };
+    let enemy_motion = state.opponent_model.stats().enemy_motion();
+    let target = receiver_positions
+        .iter()
+        .max_by(|a, b| {
+            let score = |p: &Point| {
+                score_pass(
+                    &pass_to(world, p),
+                    &world.field,
+                    &receivers,
+                    &enemies,
+                    &enemy_motion,
+                    &state.rules,
+                )
+            };
+            score(a).total_cmp(&score(b))
+        })
+        .copied()
+        .unwrap_or(world.field.enemy_goal_center());
+
+    // Waiting behind the ball for a sideways pass would put the kicker over the halfway line
+    let kicker = match kick_or_wait(world, &target, PASS_SPEED) {
+        Tactic::Move((p, orientation)) => Tactic::Move((
+            Point {
+                x: side * (p.x * side).max(robot_radius + POSITION_MARGIN),
+                y: p.y,
+            },
+            orientation,
+        )),
+        tactic => tactic,
+    };
+
+    let mut optimized: Vec<Tactic> = receiver_positions
+        .iter()
+        .map(|p| Tactic::Move((*p, (ball - p).orientation())))
+        .collect();
+    let mut defense = defense::run(world, state).optimized;
+    defense.truncate(num_players.saturating_sub(1 + optimized.len()));
+    optimized.extend(defense);
+
+    RequestedTactics {
+        goalie: Some(Tactic::Goalie),
+        kicker: Some(kicker),
+        greedy: vec![],
+        optimized,
+    }
+}
+
+// The rules keep us out of their half and the center circle, so we just defend
+pub fn their_kickoff(world: &World, state: &State) -> RequestedTactics {
+    defense::run_within_rules(world, state)
+}
+
+pub fn our_free_kick(world: &World, state: &State) -> RequestedTactics {
+    let kicker = match closest_player_to_ball(world, None) {
+        Some(r) => r,
+        None => {
+            return RequestedTactics {
+                goalie: Some(Tactic::Goalie),
+                kicker: None,
+                greedy: vec![],
+                optimized: vec![],
+            }
+        }
+    };
+    let (receivers, enemies) = teammates_and_enemies(world, kicker.id);
+    let supporting = supporting_tactics(world, state, receivers.len());
+
+    // The kicker can't touch the ball twice, so if there's no good option
+    // pass to the most open spot a supporter is heading to
+    let (target, speed) = match shot_or_pass(world, state, kicker, &receivers, &enemies) {
+        Some(Tactic::Kick((target, speed))) => (target, speed),
+        _ => {
+            let target = receive_positions(world, state, 1)
+                .first()
+                .copied()
+                .unwrap_or(world.field.enemy_goal_center());
+            (target, PASS_SPEED)
+        }
+    };
+
+    RequestedTactics {
+        goalie: Some(Tactic::Goalie),
+        kicker: Some(kick_or_wait(world, &target, speed)),
+        greedy: vec![],
+        optimized: supporting,
+    }
+}
+
+// The rules keep us away from the ball, so we just defend
+pub fn their_free_kick(world: &World, state: &State) -> RequestedTactics {
+    defense::run_within_rules(world, state)
+}
+
+pub fn our_penalty(world: &World, state: &State) -> RequestedTactics {
+    let robot_radius = state.rules.robot_max_radius;
+    let ball = world.ball.position;
+    let kicker_id = closest_player_to_ball(world, None).map(|r| r.id);
+    let shot = best_shot_on_goal(world, &ball, kicker_id, &state.rules);
+    let target = shot
+        .as_ref()
+        .map(|s| s.target)
+        .unwrap_or(world.field.enemy_goal_center());
+    let kick_speed = state.rules.max_kick_speed - KICK_SPEED_MARGIN;
+    let shot_probability = shot.map_or(0.0, |s| s.score_probability);
+    // Where the rules let us, get closer to the goal first if the shot isn't good enough yet
+    let kicker = match state.division_rules.penalty_procedure {
+        PenaltyProcedure::DribbleThenShoot
+            if world.game_state.can_manipulate_ball()
+                && shot_probability < state.strategy.min_shot_score_probability =>
+        {
+            let toward_goal = world.field.enemy_goal_center() - ball;
+            Tactic::Dribble((
+                ball + toward_goal.norm(PENALTY_DRIBBLE_DIST),
+                toward_goal.orientation(),
+            ))
+        }
+        _ => kick_or_wait(world, &target, kick_speed),
+    };
+
+    let num_players = world.friendly_team.players().len();
+    let line_x = ball.x
+        + world.field.friendly_side()
+            * (state.rules.penalty_min_dist_behind_ball + robot_radius + POSITION_MARGIN);
+    RequestedTactics {
+        goalie: Some(Tactic::Goalie),
+        kicker: Some(kicker),
+        greedy: vec![],
+        optimized: line_across_field(world, line_x, num_players.saturating_sub(1)),
+    }
+}
+
+pub fn their_penalty(world: &World, state: &State) -> RequestedTactics {
+    let robot_radius = state.rules.robot_max_radius;
+    let ball = world.ball.position;
+    let goal = world.field.friendly_goal_center();
+    // The goalie has to be touching the goal line until the ball is kicked
+    let max_goalie_y = world.field.goal_y_length / 2.0 - robot_radius;
+    let goalie_position = Point {
+        x: goal.x,
+        y: ball.y.clamp(-max_goalie_y, max_goalie_y),
+    };
+
+    let num_players = world.friendly_team.players().len();
+    let line_x = ball.x
+        - world.field.friendly_side()
+            * (state.rules.penalty_min_dist_behind_ball + robot_radius + POSITION_MARGIN);
+    RequestedTactics {
+        goalie: Some(Tactic::Move((
+            goalie_position,
+            (ball - goalie_position).orientation(),
+        ))),
+        kicker: None,
+        greedy: vec![],
+        optimized: line_across_field(world, line_x, num_players),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_config;
+    use crate::geom::Angle;
+    use crate::motion::KinematicState;
+    use crate::proto::ssl_gamecontroller::referee::Command;
+    use crate::world::{Ball, BallFriction, Field, GameState, Robot, Team, TeamInfo};
+    use std::collections::HashMap;
+
+    fn robot(id: usize, position: Point) -> Robot {
+        Robot {
+            id,
+            state: KinematicState {
+                position,
+                orientation: Angle::zero(),
+                velocity: Vector::new(),
+                angular_velocity: Angle::zero(),
+            },
+        }
+    }
+
+    fn world(friendly: Vec<Robot>, enemy: Vec<Robot>, game_state: GameState) -> World {
+        let team_info = TeamInfo {
+            name: String::new(),
+            is_blue: true,
+            score: 0,
+            goalie_id: 0,
+            defending_positive_side: false,
+        };
+        World {
+            field: Field::ssl_div_b(),
+            ball: Ball {
+                position: Point { x: 0.0, y: 0.0 },
+                velocity: Vector::new(),
+                friction: BallFriction {
+                    sliding_deceleration: 3.0,
+                    rolling_deceleration: 0.5,
+                    rolling_speed: 2.0,
+                },
+            },
+            friendly_team: Team::new().with_robots(friendly).with_goalie(0).build(),
+            enemy_team: Team::new().with_robots(enemy).build(),
+            game_state,
+            friendly_team_info: team_info.clone(),
+            enemy_team_info: team_info,
+        }
+    }
+
+    #[test]
+    fn kickoff_positions_stay_in_our_half() {
+        let state = State::new(&load_config().unwrap());
+        for defending_positive_side in [false, true] {
+            let side = if defending_positive_side { 1.0 } else { -1.0 };
+            let mut game_state = GameState::new();
+            game_state.update_command(Command::PrepareKickoffBlue, true);
+            let line = |x: f64| -> Vec<Robot> {
+                (0..6)
+                    .map(|id| {
+                        robot(
+                            id,
+                            Point {
+                                x,
+                                y: id as f64 - 2.5,
+                            },
+                        )
+                    })
+                    .collect()
+            };
+            let mut world = world(line(side * 3.0), line(-side), game_state);
+            world.field.defending_positive_side = defending_positive_side;
+            world.friendly_team_info.defending_positive_side = defending_positive_side;
+            world.enemy_team_info.defending_positive_side = !defending_positive_side;
+            let tactics = our_kickoff(&world, &state);
+
+            let mut positions = vec![];
+            for tactic in tactics.kicker.iter().chain(tactics.optimized.iter()) {
+                match tactic {
+                    Tactic::Move((p, _)) => positions.push(*p),
+                    _ => panic!("Expected every robot to move into position before the kickoff"),
+                }
+            }
+            assert_eq!(positions.len(), 5);
+            for p in positions {
+                assert!(p.x * side > 0.0, "{:?} is in the enemy half", p);
+            }
+        }
+    }
+
+    fn restart_world(commands: &[Command], ball: Point, friendly: Vec<Robot>) -> World {
+        let mut game_state = GameState::new();
+        for c in commands {
+            game_state.update_command(*c, true);
+        }
+        let enemy = vec![
+            robot(0, Point { x: 4.3, y: 0.0 }),
+            robot(1, ball + Vector::from_angle(Angle::zero(), 0.6)),
+            robot(2, Point { x: -1.0, y: 2.0 }),
+        ];
+        let mut world = world(friendly, enemy, game_state);
+        world.ball.position = ball;
+        world
+    }
+
+    fn spread_out_robots() -> Vec<Robot> {
+        (0..6)
+            .map(|id| {
+                robot(
+                    id,
+                    Point {
+                        x: -2.0,
+                        y: id as f64 - 2.5,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn move_positions(tactics: &[Tactic]) -> Vec<Point> {
+        tactics
+            .iter()
+            .map(|t| match t {
+                Tactic::Move((p, _)) => *p,
+                _ => panic!("Expected robots to move into position"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn robot_that_placed_the_ball_takes_the_free_kick() {
+        let state = State::new(&load_config().unwrap());
+        let ball = Point { x: 1.0, y: 0.5 };
+        let mut friendly = spread_out_robots();
+        // Still sitting behind the ball it just placed
+        friendly[3] = robot(
+            3,
+            Point {
+                x: ball.x - 0.1,
+                y: ball.y,
+            },
+        );
+        let world = restart_world(
+            &[Command::BallPlacementBlue, Command::DirectFreeBlue],
+            ball,
+            friendly,
+        );
+        let tactics = our_free_kick(&world, &state);
+        assert!(matches!(tactics.kicker, Some(Tactic::Kick(_))));
+        assert_eq!(tactics.optimized.len(), 4);
+        // Nobody else gets in the kicker's way
+        for p in move_positions(&tactics.optimized) {
+            assert!((p - ball).length() > KICKER_STAGING_DIST + 2.0 * state.rules.robot_max_radius);
+        }
+
+        let robots = world
+            .friendly_team
+            .all_robots()
+            .into_iter()
+            .map(|r| (r.id, r))
+            .collect();
+        let (_, kicker_id) =
+            crate::gameplay::assign_robots_to_tactics(tactics, robots, &world, &HashMap::new());
+        assert_eq!(kicker_id, Some(3));
+    }
+
+    #[test]
+    fn robots_keep_away_from_ball_for_their_free_kick() {
+        let state = State::new(&load_config().unwrap());
+        // Close enough to our defense area that the ball defenders would be too close
+        let ball = Point { x: -3.0, y: 0.5 };
+        let world = restart_world(&[Command::DirectFreeYellow], ball, spread_out_robots());
+        let tactics = their_free_kick(&world, &state);
+        assert!(tactics.kicker.is_none());
+        assert!(matches!(tactics.goalie, Some(Tactic::Goalie)));
+        let min_dist = state.rules.stop_min_dist_to_ball + state.rules.robot_max_radius;
+        let positions = move_positions(&tactics.optimized);
+        assert_eq!(positions.len(), 5);
+        for p in positions {
+            assert!(
+                (p - ball).length() >= min_dist - 1e-6,
+                "{:?} is too close to the ball",
+                p
+            );
+        }
+    }
+
+    #[test]
+    fn our_penalty_kicker_waits_then_shoots() {
+        let state = State::new(&load_config().unwrap());
+        let field = Field::ssl_div_b();
+        let ball = field.enemy_penalty_mark();
+        let dist_behind = state.rules.penalty_min_dist_behind_ball + state.rules.robot_max_radius;
+
+        let world = restart_world(&[Command::PreparePenaltyBlue], ball, spread_out_robots());
+        let tactics = our_penalty(&world, &state);
+        match tactics.kicker {
+            Some(Tactic::Move((p, _))) => {
+                assert!(((p - ball).length() - KICKER_STAGING_DIST).abs() < 1e-6);
+                // Behind the ball, on the side of our goal
+                assert!(p.x < ball.x);
+            }
+            _ => panic!("Expected the kicker to wait for the referee"),
+        }
+        // Everyone else lines up behind the ball
+        let positions = move_positions(&tactics.optimized);
+        assert_eq!(positions.len(), 4);
+        for p in positions {
+            assert!(
+                ball.x - p.x >= dist_behind,
+                "{:?} is too close to the ball",
+                p
+            );
+        }
+
+        let world = restart_world(
+            &[Command::PreparePenaltyBlue, Command::NormalStart],
+            ball,
+            spread_out_robots(),
+        );
+        match our_penalty(&world, &state).kicker {
+            Some(Tactic::Kick((target, _))) => assert!(target.x > ball.x),
+            Some(Tactic::Dribble((target, _))) => assert!(target.x > ball.x),
+            _ => panic!("Expected the kicker to go for goal"),
+        }
+    }
+
+    #[test]
+    fn their_penalty_goalie_on_line_and_others_behind_ball() {
+        let state = State::new(&load_config().unwrap());
+        let field = Field::ssl_div_b();
+        let mark = field.friendly_penalty_mark();
+        let ball = Point {
+            x: mark.x,
+            y: mark.y + 0.3,
+        };
+        let dist_behind = state.rules.penalty_min_dist_behind_ball + state.rules.robot_max_radius;
+
+        let world = restart_world(&[Command::PreparePenaltyYellow], ball, spread_out_robots());
+        let tactics = their_penalty(&world, &state);
+        assert!(tactics.kicker.is_none());
+        match tactics.goalie {
+            Some(Tactic::Move((p, _))) => {
+                let goal = field.friendly_goal_center();
+                assert_eq!(p.x, goal.x);
+                assert!((p.y - ball.y).abs() < 1e-6);
+            }
+            _ => panic!("Expected the goalie to stand on the goal line"),
+        }
+        // Behind the ball from the kicker's point of view, away from our goal
+        let positions = move_positions(&tactics.optimized);
+        assert_eq!(positions.len(), 5);
+        for p in positions {
+            assert!(
+                p.x - ball.x >= dist_behind,
+                "{:?} is too close to the ball",
+                p
+            );
+        }
+    }
+}
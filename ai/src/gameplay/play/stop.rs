@@ -0,0 +1,223 @@
+use super::RequestedTactics;
+use crate::gameplay::tactic::Tactic;
+use crate::gameplay::world::World;
+use crate::gameplay::State;
+use crate::geom::{Angle, Point, Vector};
+use crate::motion::constraints::MotionConstraints;
+
+// Extra distance kept from the ball on top of what the rules require
+const BALL_DIST_MARGIN: f64 = 0.15;
+const MAX_WALL_SIZE: usize = 3;
+// Gap between the robots in the wall
+const WALL_SPACING: f64 = 0.02;
+// The rest of the robots spread out in an arc towards the enemy goal,
+// ready to attack when the game restarts
+const SPREAD_RADIUS: f64 = 1.5;
+const SPREAD_MAX_ANGLE_DEG: f64 = 75.0;
+const FIELD_EDGE_MARGIN: f64 = 0.1;
+// Where to look for a legal position when the constraints can't be satisfied directly
+const NUM_SEARCH_RINGS: usize = 12;
+const NUM_SEARCH_ANGLES: usize = 24;
+const SEARCH_RING_SPACING: f64 = 0.25;
+
+// Moves the point somewhere the robot is allowed to be in the current game state, inside
+// the field
+pub fn legal_position(world: &World, state: &State, p: &Point) -> Point {
+    let constraints = MotionConstraints::from_game_state(
+        false,
+        false,
+        &world.game_state,
+        &world.field,
+        &world.ball,
+        &state.rules,
+    );
+    let field = world
+        .field
+        .touch_lines()
+        .expand(-state.rules.robot_max_radius - FIELD_EDGE_MARGIN);
+    let valid = constraints.closest_valid_point(&field.clamp(p));
+    if field.contains(&valid) && constraints.is_valid(&valid) {
+        return valid;
+    }
+    // Squeezed between the ball, a defense area or the field edge, so look for the closest
+    // spot around the ball that works
+    let ball = world.ball.position;
+    (1..=NUM_SEARCH_RINGS)
+        .flat_map(|ring| {
+            (0..NUM_SEARCH_ANGLES).map(move |i| {
+                ball + Vector::from_angle(
+                    Angle::full() / NUM_SEARCH_ANGLES * i,
+                    ring as f64 * SEARCH_RING_SPACING,
+                )
+            })
+        })
+        .filter(|c| field.contains(c) && constraints.is_valid(c))
+        .min_by(|a, b| (a - p).length().total_cmp(&(b - p).length()))
+        .unwrap_or(field.clamp(&valid))
+}
+
+fn wall_positions(world: &World, state: &State, num_robots: usize) -> Vec<Point> {
+    let robot_radius = state.rules.robot_max_radius;
+    let ball = world.ball.position;
+    let ball_to_goal = world.field.friendly_goal_center() - ball;
+    let dist_from_ball = state.rules.stop_min_dist_to_ball + robot_radius + BALL_DIST_MARGIN;
+    let center = ball + ball_to_goal.norm(dist_from_ball);
+    let sideways = ball_to_goal
+        .perpendicular()
+        .norm(2.0 * robot_radius + WALL_SPACING);
+    (0..num_robots)
+        .map(|i| {
+            let offset = i as f64 - (num_robots - 1) as f64 / 2.0;
+            legal_position(world, state, &(center + sideways * offset))
+        })
+        .collect()
+}
+
+fn spread_positions(world: &World, state: &State, num_robots: usize) -> Vec<Point> {
+    let ball = world.ball.position;
+    let forward = (world.field.enemy_goal_center() - ball).orientation();
+    (0..num_robots)
+        .map(|i| {
+            let fraction = if num_robots > 1 {
+                i as f64 / (num_robots - 1) as f64
+            } else {
+                0.5
+            };
+            let angle =
+                forward + Angle::from_degrees(SPREAD_MAX_ANGLE_DEG * (2.0 * fraction - 1.0));
+            legal_position(
+                world,
+                state,
+                &(ball + Vector::from_angle(angle, SPREAD_RADIUS)),
+            )
+        })
+        .collect()
+}
+
+pub fn run(world: &World, state: &State) -> RequestedTactics {
+    let ball = world.ball.position;
+    let num_players = world.friendly_team.players().len();
+    let num_wall = num_players.div_ceil(2).min(MAX_WALL_SIZE);
+    let positions = wall_positions(world, state, num_wall)
+        .into_iter()
+        .chain(spread_positions(world, state, num_players - num_wall));
+
+    RequestedTactics {
+        goalie: Some(Tactic::Goalie),
+        kicker: None,
+        greedy: vec![],
+        optimized: positions
+            .map(|p| Tactic::Move((p, (ball - p).orientation())))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_config;
+    use crate::motion::KinematicState;
+    use crate::proto::ssl_gamecontroller::referee::Command;
+    use crate::world::{Ball, BallFriction, Field, GameState, Robot, Team, TeamInfo};
+
+    fn robot(id: usize, position: Point) -> Robot {
+        Robot {
+            id,
+            state: KinematicState {
+                position,
+                orientation: Angle::zero(),
+                velocity: Vector::new(),
+                angular_velocity: Angle::zero(),
+            },
+        }
+    }
+
+    fn stopped_world(ball: Point) -> World {
+        let team_info = TeamInfo {
+            name: String::new(),
+            is_blue: true,
+            score: 0,
+            goalie_id: 0,
+            defending_positive_side: false,
+        };
+        let mut game_state = GameState::new();
+        game_state.update_command(Command::Stop, true);
+        let friendly = (0..6)
+            .map(|id| {
+                robot(
+                    id,
+                    Point {
+                        x: -2.0,
+                        y: id as f64 - 2.5,
+                    },
+                )
+            })
+            .collect();
+        World {
+            field: Field::ssl_div_b(),
+            ball: Ball {
+                position: ball,
+                velocity: Vector::new(),
+                friction: BallFriction {
+                    sliding_deceleration: 3.0,
+                    rolling_deceleration: 0.5,
+                    rolling_speed: 2.0,
+                },
+            },
+            friendly_team: Team::new().with_robots(friendly).with_goalie(0).build(),
+            enemy_team: Team::new().build(),
+            game_state,
+            friendly_team_info: team_info.clone(),
+            enemy_team_info: team_info,
+        }
+    }
+
+    #[test]
+    fn stop_positions_are_legal() {
+        let state = State::new(&load_config().unwrap());
+        // The middle of the field, in front of our defense area and in the corners,
+        // where some of the positions get pushed around
+        let balls = [
+            Point { x: 0.0, y: 0.0 },
+            Point { x: -3.0, y: 0.5 },
+            Point { x: -4.3, y: -2.8 },
+            Point { x: 4.3, y: 2.8 },
+        ];
+        for ball in balls {
+            let world = stopped_world(ball);
+            let constraints = MotionConstraints::from_game_state(
+                false,
+                false,
+                &world.game_state,
+                &world.field,
+                &world.ball,
+                &state.rules,
+            );
+            let field = world
+                .field
+                .touch_lines()
+                .expand(-state.rules.robot_max_radius);
+            let tactics = run(&world, &state);
+            assert_eq!(tactics.optimized.len(), world.friendly_team.players().len());
+            for tactic in tactics.optimized {
+                match tactic {
+                    Tactic::Move((p, _)) => {
+                        assert!(
+                            constraints.is_valid(&p),
+                            "{:?} is illegal, ball {:?}",
+                            p,
+                            ball
+                        );
+                        assert!(
+                            field.contains(&p),
+                            "{:?} is off the field, ball {:?}",
+                            p,
+                            ball
+                        );
+                    }
+                    _ => panic!("Expected every robot to move during STOP"),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,364 @@
+use crate::gameplay::evaluation::passing::Pass;
+use crate::gameplay::world::{Robot, World};
+use crate::gameplay::State;
+use crate::geom::{Angle, Point, Vector};
+use crate::motion::dribble::{
+    dribble_to, has_ball, intercept_trajectory, is_ball_moving, staging_trajectory,
+    CAPTURE_MAX_SPEED, DRIBBLER_BALL_DIST, STAGING_DIST,
+};
+use crate::motion::kick::{chip_ball, kick_ball};
+use crate::motion::planner::straight_line;
+use crate::motion::Trajectory;
+
+// How far around the ball the robot moves at a time when pivoting, so it doesn't
+// cut across the ball
+const PIVOT_MAX_STEP_DEG: f64 = 30.0;
+// A ball moving away from the robot at least this fast, and still close to it,
+// has just been kicked by it
+const KICKED_BALL_SPEED: f64 = 1.0;
+const KICKED_BALL_MAX_DIST: f64 = 0.5;
+const DRIBBLE_DONE_TOLERANCE: f64 = 0.1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SkillProgress {
+    InProgress,
+    Succeeded,
+    Failed,
+}
+
+// The building blocks tactics are made from. Each one knows how to move the robot to
+// do one thing with the ball, and how to tell whether it's worked
+#[derive(Clone, Debug)]
+pub enum Skill {
+    // Get the ball onto the dribbler
+    GoToBall,
+    // Move around the ball until the robot is behind it, facing the given orientation
+    PivotAroundBall(Angle),
+    // Kick the ball at the target with the given speed
+    Kick((Point, f64)),
+    // Chip the ball towards the target so it lands the given distance away
+    Chip((Point, f64)),
+    // Get in the way of a pass and catch it on the dribbler
+    Receive(Pass),
+    // Take the ball to the point, ending up facing the given orientation
+    DribbleTo((Point, Angle)),
+}
+
+fn enemy_has_ball(world: &World) -> bool {
+    world
+        .enemy_team
+        .all_robots()
+        .iter()
+        .any(|r| has_ball(r, &world.ball))
+}
+
+fn ball_just_kicked(robot: &Robot, world: &World) -> bool {
+    let ball = &world.ball;
+    let robot_to_ball = ball.position - robot.state.position;
+    ball.velocity.length() > KICKED_BALL_SPEED
+        && ball.velocity.dot(&robot_to_ball) > 0.0
+        && robot_to_ball.length() < KICKED_BALL_MAX_DIST
+}
+
+fn go_to_ball(robot: &Robot, world: &World) -> Trajectory {
+    let ball = &world.ball;
+    if is_ball_moving(ball) {
+        return intercept_trajectory(robot, ball);
+    }
+    let robot_to_ball = ball.position - robot.state.position;
+    let position = ball.position + robot_to_ball.norm(-DRIBBLER_BALL_DIST);
+    let mut traj = straight_line(&robot.state, &position, &robot_to_ball.orientation());
+    traj.dribble = true;
+    if robot_to_ball.length() < STAGING_DIST {
+        traj.max_speed = Some(CAPTURE_MAX_SPEED);
+    }
+    traj
+}
+
+fn pivot_around_ball(robot: &Robot, world: &World, orientation: &Angle) -> Trajectory {
+    let ball = &world.ball;
+    let current_angle = (robot.state.position - ball.position).orientation();
+    let target_angle = *orientation + Angle::half();
+    let remaining = (target_angle - current_angle).clamp_pos_neg_pi();
+    let max_step = Angle::from_degrees(PIVOT_MAX_STEP_DEG);
+    if remaining.radians().abs() < max_step.radians() {
+        if let Some(traj) = staging_trajectory(robot, ball, orientation) {
+            return traj;
+        }
+        return straight_line(&robot.state, &robot.state.position, orientation);
+    }
+    let step = remaining.min(max_step).max(-max_step);
+    let position = ball.position + Vector::from_angle(current_angle + step, STAGING_DIST);
+    straight_line(
+        &robot.state,
+        &position,
+        &(ball.position - position).orientation(),
+    )
+}
+
+// Whether the robot is so far round the wrong side of the ball that heading straight for
+// the kicking position would take it across the ball
+fn needs_pivot(robot: &Robot, world: &World, orientation: &Angle) -> bool {
+    let ball = &world.ball;
+    if is_ball_moving(ball) {
+        return false;
+    }
+    let current_angle = (robot.state.position - ball.position).orientation();
+    let target_angle = *orientation + Angle::half();
+    (target_angle - current_angle)
+        .clamp_pos_neg_pi()
+        .degrees()
+        .abs()
+        > PIVOT_MAX_STEP_DEG
+}
+
+// Waits on the pass line for the ball, or where the ball is actually going once it's moving
+fn receive(robot: &Robot, world: &World, pass: &Pass) -> Trajectory {
+    let ball = &world.ball;
+    let (origin, direction) = if is_ball_moving(ball) {
+        (ball.position, ball.velocity)
+    } else {
+        (pass.start, pass.end - pass.start)
+    };
+    let position = if direction.length() > 1.0e-6 {
+        let along = (robot.state.position - origin).dot(&direction.norm(1.0));
+        origin + direction.norm(along.max(0.0))
+    } else {
+        pass.end
+    };
+    let mut traj = straight_line(&robot.state, &position, &(origin - position).orientation());
+    traj.dribble = true;
+    traj
+}
+
+impl Skill {
+    pub fn trajectory(&self, robot: &Robot, world: &World, state: &State) -> Trajectory {
+        let ball = &world.ball;
+        match self {
+            Self::GoToBall => go_to_ball(robot, world),
+            Self::PivotAroundBall(a) => pivot_around_ball(robot, world, a),
+            Self::Kick((p, _)) | Self::Chip((p, _))
+                if needs_pivot(robot, world, &(p - &ball.position).orientation()) =>
+            {
+                pivot_around_ball(robot, world, &(p - &ball.position).orientation())
+            }
+            Self::Kick((p, speed)) => kick_ball(robot, ball, p, *speed),
+            Self::Chip((p, distance)) => chip_ball(robot, ball, p, *distance),
+            // The pass is going to stop short of us or has already gone past, so go and get it
+            Self::Receive(_)
+                if is_ball_moving(ball) && ball.time_to_reach(&robot.state.position).is_none() =>
+            {
+                go_to_ball(robot, world)
+            }
+            Self::Receive(pass) => receive(robot, world, pass),
+            Self::DribbleTo((p, a)) => dribble_to(
+                robot,
+                ball,
+                p,
+                a,
+                state.dribble_tracker.capture_position(robot.id),
+                state.rules.max_dribble_distance,
+            ),
+        }
+    }
+
+    pub fn progress(&self, robot: &Robot, world: &World, state: &State) -> SkillProgress {
+        let ball = &world.ball;
+        let succeeded = match self {
+            Self::GoToBall | Self::Receive(_) => has_ball(robot, ball),
+            Self::PivotAroundBall(a) => staging_trajectory(robot, ball, a).is_none(),
+            Self::Kick(_) | Self::Chip(_) => ball_just_kicked(robot, world),
+            Self::DribbleTo((p, _)) => {
+                has_ball(robot, ball) && (p - &ball.position).length() < DRIBBLE_DONE_TOLERANCE
+            }
+        };
+        let failed = match self {
+            Self::PivotAroundBall(_) => false,
+            Self::DribbleTo(_) => {
+                enemy_has_ball(world)
+                    || state
+                        .dribble_tracker
+                        .distance_dribbled(robot.id, ball)
+                        .map_or(false, |d| d >= state.rules.max_dribble_distance)
+            }
+            _ => enemy_has_ball(world),
+        };
+        if succeeded {
+            SkillProgress::Succeeded
+        } else if failed {
+            SkillProgress::Failed
+        } else {
+            SkillProgress::InProgress
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_config;
+    use crate::motion::KinematicState;
+    use crate::world::{Ball, BallFriction, Field, GameState, Team, TeamInfo};
+
+    fn robot(position: Point, orientation: Angle) -> Robot {
+        Robot {
+            id: 1,
+            state: KinematicState {
+                position,
+                orientation,
+                velocity: Vector::new(),
+                angular_velocity: Angle::zero(),
+            },
+        }
+    }
+
+    fn world(ball: Point, velocity: Vector) -> World {
+        let team_info = TeamInfo {
+            name: String::new(),
+            is_blue: true,
+            score: 0,
+            goalie_id: 0,
+            defending_positive_side: false,
+        };
+        World {
+            field: Field::ssl_div_b(),
+            ball: Ball {
+                position: ball,
+                velocity,
+                friction: BallFriction {
+                    sliding_deceleration: 3.0,
+                    rolling_deceleration: 0.5,
+                    rolling_speed: 2.0,
+                },
+            },
+            friendly_team: Team::new().build(),
+            enemy_team: Team::new().build(),
+            game_state: GameState::new(),
+            friendly_team_info: team_info.clone(),
+            enemy_team_info: team_info,
+        }
+    }
+
+    fn end(traj: &Trajectory) -> Point {
+        *traj.points.last().unwrap()
+    }
+
+    #[test]
+    fn go_to_ball_drives_onto_dribbler() {
+        let state = State::new(&load_config().unwrap());
+        let world = world(Point { x: 1.0, y: 0.0 }, Vector::new());
+        let r = robot(Point::new(), Angle::zero());
+        let traj = Skill::GoToBall.trajectory(&r, &world, &state);
+        assert!(traj.dribble);
+        assert!(
+            (end(&traj)
+                - Point {
+                    x: 1.0 - DRIBBLER_BALL_DIST,
+                    y: 0.0
+                })
+            .length()
+                < 1.0e-6
+        );
+        assert_eq!(
+            Skill::GoToBall.progress(&r, &world, &state),
+            SkillProgress::InProgress
+        );
+
+        let r = robot(
+            Point {
+                x: 1.0 - DRIBBLER_BALL_DIST,
+                y: 0.0,
+            },
+            Angle::zero(),
+        );
+        assert_eq!(
+            Skill::GoToBall.progress(&r, &world, &state),
+            SkillProgress::Succeeded
+        );
+    }
+
+    #[test]
+    fn pivot_goes_around_ball_a_step_at_a_time() {
+        let state = State::new(&load_config().unwrap());
+        let world = world(Point::new(), Vector::new());
+        // In front of the ball, but it should end up behind it facing +x
+        let r = robot(
+            Point {
+                x: STAGING_DIST,
+                y: 0.0,
+            },
+            Angle::half(),
+        );
+        let skill = Skill::PivotAroundBall(Angle::zero());
+        let traj = skill.trajectory(&r, &world, &state);
+        let next = end(&traj) - Point::new();
+        assert!((next.length() - STAGING_DIST).abs() < 1.0e-6);
+        let step = next.orientation().clamp_pos_neg_pi().degrees().abs();
+        assert!((step - PIVOT_MAX_STEP_DEG).abs() < 1.0e-6);
+        assert_eq!(
+            skill.progress(&r, &world, &state),
+            SkillProgress::InProgress
+        );
+
+        let r = robot(
+            Point {
+                x: -STAGING_DIST,
+                y: 0.0,
+            },
+            Angle::zero(),
+        );
+        assert_eq!(skill.progress(&r, &world, &state), SkillProgress::Succeeded);
+    }
+
+    #[test]
+    fn kick_pivots_instead_of_cutting_across_ball() {
+        let state = State::new(&load_config().unwrap());
+        let world = world(Point::new(), Vector::new());
+        let r = robot(
+            Point {
+                x: STAGING_DIST,
+                y: 0.0,
+            },
+            Angle::half(),
+        );
+        let traj = Skill::Kick((Point { x: 4.5, y: 0.0 }, 3.0)).trajectory(&r, &world, &state);
+        assert!(((end(&traj) - Point::new()).length() - STAGING_DIST).abs() < 1.0e-6);
+        assert!(traj.autokick_speed.is_none());
+
+        // Already behind the ball, so it goes straight for the kick
+        let r = robot(
+            Point {
+                x: -STAGING_DIST,
+                y: 0.0,
+            },
+            Angle::zero(),
+        );
+        let traj = Skill::Kick((Point { x: 4.5, y: 0.0 }, 3.0)).trajectory(&r, &world, &state);
+        assert!(traj.autokick_speed.is_some());
+    }
+
+    #[test]
+    fn receiver_chases_pass_that_stops_short() {
+        let state = State::new(&load_config().unwrap());
+        let pass = Pass {
+            start: Point::new(),
+            end: Point { x: 3.0, y: 0.0 },
+            speed: 4.0,
+            time_offset: 0.0,
+            friction: world(Point::new(), Vector::new()).ball.friction,
+        };
+        let r = robot(Point { x: 3.0, y: 0.0 }, Angle::half());
+        let skill = Skill::Receive(pass);
+
+        // Fast enough to get there, so wait on its path
+        let fast = world(Point { x: 1.0, y: 0.0 }, Vector { x: 3.0, y: 0.0 });
+        let traj = skill.trajectory(&r, &fast, &state);
+        assert!((end(&traj) - r.state.position).length() < 1.0e-6);
+
+        // Only rolls about half a metre more, so go and get it
+        let slow = world(Point { x: 1.0, y: 0.0 }, Vector { x: 0.7, y: 0.0 });
+        let traj = skill.trajectory(&r, &slow, &state);
+        assert!(end(&traj).x < 2.0);
+        assert!(traj.dribble);
+    }
+}
@@ -1,31 +1,358 @@
+mod goalie;
+
+use crate::gameplay::evaluation::passing::Pass;
+use crate::gameplay::skill::{Skill, SkillProgress};
 use crate::gameplay::world::{Robot, World};
 use crate::gameplay::State;
 use crate::geom::{Angle, Point};
+use crate::motion::dribble::{is_ball_moving, place_ball};
 use crate::motion::planner::{stopping_trajectory, straight_line};
 use crate::motion::Trajectory;
+use std::mem::discriminant;
+use std::time::{Duration, Instant};
+
+// Move targets closer than this to the one we're already going to are ignored,
+// so noise in the play's calculations doesn't make robots twitch
+const MOVE_TARGET_DEADBAND: f64 = 0.05;
+const MOVE_DONE_TOLERANCE: f64 = 0.05;
+const KICK_TARGET_DEADBAND: f64 = 0.05;
+// Move targets within this distance of each other are treated as the same job
+const SAME_ROLE_DIST: f64 = 0.5;
+const STOPPED_SPEED: f64 = 0.1;
+// How close the robot has to get to the ball before a kick could have happened
+const KICK_CONTACT_DIST: f64 = 0.15;
 
 #[derive(Clone, Debug)]
 pub enum Tactic {
     Stop,
     Move((Point, Angle)),
+    // Take the ball to the point, ending up facing the given orientation
+    Dribble((Point, Angle)),
+    // Kick the ball at the target with the given speed
+    Kick((Point, f64)),
+    // Put the ball at the point for a ball placement, then back away from it
+    PlaceBall(Point),
+    // Catch a pass that's on its way
+    Receive(Pass),
+    // Defend the goal, and clear the ball out of the defense area
+    Goalie,
     // ShadowEnemy(Robot),
 }
 
 impl Tactic {
-    pub fn robot_assignment_cost(&self, robot: &Robot) -> f64 {
+    pub fn robot_assignment_cost(&self, robot: &Robot, world: &World) -> f64 {
         match self {
             Self::Stop => 0.5,
             // Self::Move((p, a)) => ((p - &robot.state.position).length()*1000.0).powi(2),
             Self::Move((p, a)) => (p - &robot.state.position).length(),
+            Self::Dribble(_) => (world.ball.position - robot.state.position).length(),
+            Self::Kick(_) => (world.ball.position - robot.state.position).length(),
+            // The ball might have been passed towards the target, so whoever is
+            // closest to where it stops should collect it
+            Self::PlaceBall(_) => (world.ball.stop_position() - robot.state.position).length(),
+            Self::Receive(pass) => (pass.end - robot.state.position).length(),
+            // Only ever given to the goalie, so there's no choice to make
+            Self::Goalie => 0.0,
             // Self::ShadowEnemy(r) => (r.state.position - robot.state.position).length(),
         }
     }
 
+    // Whether a robot switching from this tactic to the other one would really be
+    // changing what it's doing
+    pub fn is_same_role(&self, other: &Tactic) -> bool {
+        match (self, other) {
+            (Self::Move((p1, _)), Self::Move((p2, _))) => (p1 - p2).length() < SAME_ROLE_DIST,
+            _ => discriminant(self) == discriminant(other),
+        }
+    }
+
+    // The skill the tactic is built from, if it's more than a simple movement
+    fn skill(&self) -> Option<Skill> {
+        match self {
+            Self::Dribble(target) => Some(Skill::DribbleTo(*target)),
+            Self::Kick(target) => Some(Skill::Kick(*target)),
+            Self::Receive(pass) => Some(Skill::Receive(pass.clone())),
+            _ => None,
+        }
+    }
+
     pub fn run(&self, robot: &Robot, world: &World, state: &State) -> Trajectory {
         match self {
             Self::Stop => stopping_trajectory(&robot.state),
             Self::Move((p, a)) => straight_line(&robot.state, p, a),
+            Self::Dribble(_) | Self::Kick(_) | Self::Receive(_) => {
+                self.skill().unwrap().trajectory(robot, world, state)
+            }
+            Self::PlaceBall(p) => place_ball(
+                robot,
+                &world.ball,
+                p,
+                // Aim well within the tolerance, since the ball moves a bit when released
+                state.rules.ball_placement_tolerance / 2.0,
+                state.rules.robot_max_radius
+                    + state.rules.ball_max_radius
+                    + state.rules.ball_placement_min_dist_to_ball,
+            ),
+            Self::Goalie => goalie::run(robot, world, state),
             // Self::ShadowEnemy(r) => straight_line(&robot.state, &r.state.position, &Angle::zero()),
         }
     }
 }
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TacticStatus {
+    Running,
+    Done,
+    Failed,
+}
+
+// A tactic assigned to a particular robot. It lives for as long as the robot keeps
+// being given the same kind of tactic, so it can remember things between ticks
+pub struct ActiveTactic {
+    tactic: Tactic,
+    status: TacticStatus,
+    start_time: Instant,
+    // Whether the robot has been right next to the ball since the tactic started
+    touched_ball: bool,
+}
+
+impl ActiveTactic {
+    pub fn start(tactic: Tactic) -> ActiveTactic {
+        ActiveTactic {
+            tactic,
+            status: TacticStatus::Running,
+            start_time: Instant::now(),
+            touched_ball: false,
+        }
+    }
+
+    pub fn is_same_kind(&self, tactic: &Tactic) -> bool {
+        discriminant(&self.tactic) == discriminant(tactic)
+    }
+
+    // Takes the latest parameters from the play without forgetting what we've done so far
+    pub fn set_tactic(&mut self, tactic: Tactic) {
+        if let (Tactic::Move((old_p, _)), Tactic::Move((new_p, new_a))) = (&self.tactic, &tactic) {
+            if (new_p - old_p).length() < MOVE_TARGET_DEADBAND {
+                // Only the position is noisy, the robot should still turn to face the new way
+                self.tactic = Tactic::Move((*old_p, *new_a));
+                return;
+            }
+        }
+        if !self.tactic.is_same_role(&tactic) {
+            self.start_time = Instant::now();
+        }
+        // A kick somewhere else is a new kick, even if this robot has just made one
+        if let (Tactic::Kick((old_p, old_speed)), Tactic::Kick((new_p, new_speed))) =
+            (&self.tactic, &tactic)
+        {
+            if (new_p - old_p).length() > KICK_TARGET_DEADBAND || new_speed != old_speed {
+                self.status = TacticStatus::Running;
+                self.touched_ball = false;
+            }
+        }
+        self.tactic = tactic;
+    }
+
+    pub fn tactic(&self) -> &Tactic {
+        &self.tactic
+    }
+
+    pub fn status(&self) -> TacticStatus {
+        self.status
+    }
+
+    // How long the robot has been doing its current job
+    pub fn running_time(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
+    pub fn update(&mut self, robot: &Robot, world: &World, state: &State) -> Trajectory {
+        let ball = &world.ball;
+        if (ball.position - robot.state.position).length() < KICK_CONTACT_DIST {
+            self.touched_ball = true;
+        }
+        self.status = self.evaluate_status(robot, world, state);
+        match (&self.tactic, self.status) {
+            // Don't chase the ball after kicking it. The kick stays done until the play
+            // gives the robot something else to do
+            (Tactic::Kick(_), TacticStatus::Done) => stopping_trajectory(&robot.state),
+            _ => self.tactic.run(robot, world, state),
+        }
+    }
+
+    fn evaluate_status(&self, robot: &Robot, world: &World, state: &State) -> TacticStatus {
+        let ball = &world.ball;
+        let skill_progress = self.tactic.skill().map(|s| s.progress(robot, world, state));
+        match &self.tactic {
+            Tactic::Stop => {
+                if robot.state.velocity.length() < STOPPED_SPEED {
+                    TacticStatus::Done
+                } else {
+                    TacticStatus::Running
+                }
+            }
+            Tactic::Move((p, _)) => {
+                if (p - &robot.state.position).length() < MOVE_DONE_TOLERANCE
+                    && robot.state.velocity.length() < STOPPED_SPEED
+                {
+                    TacticStatus::Done
+                } else {
+                    TacticStatus::Running
+                }
+            }
+            // The ball might be moving away from us for other reasons, so only count the
+            // kick if we got close enough to have made it
+            Tactic::Kick(_) => match skill_progress {
+                _ if self.status == TacticStatus::Done => TacticStatus::Done,
+                Some(SkillProgress::Succeeded) if self.touched_ball => TacticStatus::Done,
+                Some(SkillProgress::Failed) => TacticStatus::Failed,
+                _ => TacticStatus::Running,
+            },
+            Tactic::Dribble(_) | Tactic::Receive(_) => match skill_progress {
+                Some(SkillProgress::Succeeded) => TacticStatus::Done,
+                Some(SkillProgress::Failed) => TacticStatus::Failed,
+                _ => TacticStatus::Running,
+            },
+            Tactic::PlaceBall(p) => {
+                let min_dist_to_ball = state.rules.robot_max_radius
+                    + state.rules.ball_max_radius
+                    + state.rules.ball_placement_min_dist_to_ball;
+                if (p - &ball.position).length() < state.rules.ball_placement_tolerance
+                    && !is_ball_moving(ball)
+                    && (ball.position - robot.state.position).length() >= min_dist_to_ball
+                {
+                    TacticStatus::Done
+                } else {
+                    TacticStatus::Running
+                }
+            }
+            // There's always a goal to defend
+            Tactic::Goalie => TacticStatus::Running,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_config;
+    use crate::geom::Vector;
+    use crate::motion::KinematicState;
+    use crate::world::{Ball, BallFriction, Field, GameState, Team, TeamInfo};
+
+    fn robot(id: usize, position: Point) -> Robot {
+        Robot {
+            id,
+            state: KinematicState {
+                position,
+                orientation: Angle::zero(),
+                velocity: Vector::new(),
+                angular_velocity: Angle::zero(),
+            },
+        }
+    }
+
+    fn world(friendly: Vec<Robot>, ball: Point) -> World {
+        let team_info = TeamInfo {
+            name: String::new(),
+            is_blue: true,
+            score: 0,
+            goalie_id: 0,
+            defending_positive_side: false,
+        };
+        World {
+            field: Field::ssl_div_b(),
+            ball: Ball {
+                position: ball,
+                velocity: Vector::new(),
+                friction: BallFriction {
+                    sliding_deceleration: 3.0,
+                    rolling_deceleration: 0.5,
+                    rolling_speed: 2.0,
+                },
+            },
+            friendly_team: Team::new().with_robots(friendly).with_goalie(0).build(),
+            enemy_team: Team::new().build(),
+            game_state: GameState::new(),
+            friendly_team_info: team_info.clone(),
+            enemy_team_info: team_info,
+        }
+    }
+
+    #[test]
+    fn small_move_target_changes_are_ignored() {
+        let mut active = ActiveTactic::start(Tactic::Move((Point::new(), Angle::zero())));
+        let nearby = Point { x: 0.01, y: 0.0 };
+        assert!(active.is_same_kind(&Tactic::Move((nearby, Angle::zero()))));
+        active.set_tactic(Tactic::Move((nearby, Angle::zero())));
+        assert!(matches!(active.tactic, Tactic::Move((p, _)) if p.x == 0.0));
+
+        let far = Point { x: 1.0, y: 0.0 };
+        active.set_tactic(Tactic::Move((far, Angle::zero())));
+        assert!(matches!(active.tactic, Tactic::Move((p, _)) if p.x == 1.0));
+        assert!(!active.is_same_kind(&Tactic::Stop));
+    }
+
+    #[test]
+    fn small_move_target_change_still_updates_orientation() {
+        let mut active = ActiveTactic::start(Tactic::Move((Point::new(), Angle::zero())));
+        let nearby = Point { x: 0.01, y: 0.0 };
+        let facing = Angle::from_degrees(90.0);
+        active.set_tactic(Tactic::Move((nearby, facing)));
+        match active.tactic {
+            Tactic::Move((p, a)) => {
+                assert_eq!(p.x, 0.0);
+                assert_eq!(a.degrees(), facing.degrees());
+            }
+            _ => panic!("Expected the move to be kept"),
+        }
+    }
+
+    #[test]
+    fn kick_stays_done_after_ball_stops() {
+        let state = State::new(&load_config().unwrap());
+        let kicker = robot(1, Point { x: 0.0, y: 0.0 });
+        // The ball has come to rest after the kick
+        let world = world(vec![kicker.clone()], Point { x: 0.5, y: 0.0 });
+        let target = Point { x: 4.5, y: 0.0 };
+        let mut active = ActiveTactic::start(Tactic::Kick((target, 3.0)));
+        active.status = TacticStatus::Done;
+        active.touched_ball = true;
+        for _ in 0..3 {
+            active.set_tactic(Tactic::Kick((target, 3.0)));
+            let trajectory = active.update(&kicker, &world, &state);
+            assert_eq!(active.status(), TacticStatus::Done);
+            assert!(trajectory
+                .points
+                .iter()
+                .all(|p| *p == kicker.state.position));
+        }
+    }
+
+    #[test]
+    fn new_kick_starts_fresh() {
+        let state = State::new(&load_config().unwrap());
+        let kicker = robot(1, Point { x: 0.0, y: 0.0 });
+        // The ball has bounced back to the robot that already kicked it
+        let world = world(vec![kicker.clone()], Point { x: 0.5, y: 0.0 });
+        let mut active = ActiveTactic::start(Tactic::Kick((Point { x: 4.5, y: 0.0 }, 3.0)));
+        active.status = TacticStatus::Done;
+        active.touched_ball = true;
+
+        active.set_tactic(Tactic::Kick((Point { x: 4.5, y: 0.5 }, 3.0)));
+        assert_eq!(active.status(), TacticStatus::Running);
+        assert!(!active.touched_ball);
+        let trajectory = active.update(&kicker, &world, &state);
+        assert_eq!(active.status(), TacticStatus::Running);
+        assert!(trajectory
+            .points
+            .iter()
+            .any(|p| *p != kicker.state.position));
+
+        // A different speed is a new kick too
+        active.status = TacticStatus::Done;
+        active.set_tactic(Tactic::Kick((Point { x: 4.5, y: 0.5 }, 4.0)));
+        assert_eq!(active.status(), TacticStatus::Running);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::gameplay::skill::Skill;
+use crate::gameplay::world::{Robot, World};
+use crate::gameplay::State;
+use crate::geom::{Angle, Point, Vector};
+use crate::motion::dribble::is_ball_moving;
+use crate::motion::planner::straight_line;
+use crate::motion::Trajectory;
+
+// Gap between the goalie and the edge of the defense area
+const ARC_MARGIN: f64 = 0.05;
+// Shots this far outside the posts are still blocked, since the ball prediction isn't perfect
+const GOAL_POST_MARGIN: f64 = 0.1;
+// Clearances go up the side of the field the ball is on, away from the middle
+// where the enemy is most likely to be waiting
+const CLEAR_CHIP_DISTANCE: f64 = 2.0;
+const CLEAR_TARGET_Y_FRACTION: f64 = 0.3;
+
+// The arc is centered on the goal and sized to fit inside the defense area
+fn arc_radius(world: &World, robot_radius: f64) -> f64 {
+    let field = &world.field;
+    field.defense_x_length.min(field.defense_y_length / 2.0) - robot_radius - ARC_MARGIN
+}
+
+// 1.0 if the field is in the +x direction from our goal, -1.0 otherwise
+fn into_field(world: &World) -> f64 {
+    -world.field.friendly_goal_center().x.signum()
+}
+
+// Where the line first enters the circle, if it does
+fn first_circle_crossing(
+    center: &Point,
+    radius: f64,
+    origin: &Point,
+    direction: &Vector,
+) -> Option<Point> {
+    if direction.length() < 1.0e-6 {
+        return None;
+    }
+    let direction = direction.norm(1.0);
+    let to_origin = origin - center;
+    let b = direction.dot(&to_origin);
+    let c = to_origin.dot(&to_origin) - radius * radius;
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t = -b - discriminant.sqrt();
+    if t < 0.0 {
+        return None;
+    }
+    Some(*origin + direction * t)
+}
+
+// Where the ball will cross our goal line if it's heading into the goal
+pub fn predicted_goal_line_crossing(world: &World) -> Option<Point> {
+    let ball = &world.ball;
+    let goal = world.field.friendly_goal_center();
+    if !is_ball_moving(ball) || ball.velocity.x * into_field(world) >= 0.0 {
+        return None;
+    }
+    let time_to_goal_line = (goal.x - ball.position.x) / ball.velocity.x;
+    let y = ball.position.y + ball.velocity.y * time_to_goal_line;
+    if y.abs() < world.field.goal_y_length / 2.0 + GOAL_POST_MARGIN {
+        Some(Point { x: goal.x, y })
+    } else {
+        None
+    }
+}
+
+// Blocks the ball's path to the goal from a point on the arc
+pub fn block_position(world: &World, robot_radius: f64) -> Point {
+    let goal = world.field.friendly_goal_center();
+    let ball = world.ball.position;
+    let radius = arc_radius(world, robot_radius);
+
+    let aim_point = match predicted_goal_line_crossing(world) {
+        Some(crossing) => crossing,
+        None => {
+            // Split the difference between the angles to each post
+            let half_goal_width = Vector {
+                x: 0.0,
+                y: world.field.goal_y_length / 2.0,
+            };
+            let to_left_post = (goal + half_goal_width - ball).orientation();
+            let to_right_post = (goal + (-half_goal_width) - ball).orientation();
+            let bisector = to_right_post + (to_left_post - to_right_post).clamp_pos_neg_pi() / 2.0;
+            let dist_to_goal_line = (goal.x - ball.x) / bisector.cos();
+            ball + Vector::from_angle(bisector, dist_to_goal_line)
+        }
+    };
+    if let Some(p) = first_circle_crossing(&goal, radius, &ball, &(aim_point - ball)) {
+        return p;
+    }
+
+    // The ball is inside the arc or behind the goal line, so stay between it and the goal
+    let max_angle = Angle::half() / 2.0;
+    let forward = Vector {
+        x: into_field(world),
+        y: 0.0,
+    }
+    .orientation();
+    let angle = ((ball - goal).orientation() - forward).clamp_pos_neg_pi();
+    let angle = angle.min(max_angle).max(-max_angle);
+    goal + Vector::from_angle(forward + angle, radius)
+}
+
+fn clear_target(world: &World) -> Point {
+    let ball = world.ball.position;
+    let side = if ball.y >= 0.0 { 1.0 } else { -1.0 };
+    Point {
+        x: ball.x + into_field(world) * CLEAR_CHIP_DISTANCE,
+        y: side * world.field.y_length * CLEAR_TARGET_Y_FRACTION,
+    }
+}
+
+// Only the goalie may touch the ball inside the defense area, so it has to get the ball
+// out when it stops there. It's chipped straight away rather than dribbled, since the
+// goalie isn't allowed to hold on to it
+pub fn run(robot: &Robot, world: &World, state: &State) -> Trajectory {
+    let ball = &world.ball;
+    let ball_stopped_in_area =
+        !is_ball_moving(ball) && world.field.friendly_defense_area().contains(&ball.position);
+    if world.game_state.playing() && ball_stopped_in_area {
+        let target = clear_target(world);
+        return Skill::Chip((target, CLEAR_CHIP_DISTANCE)).trajectory(robot, world, state);
+    }
+
+    let position = block_position(world, state.rules.robot_max_radius);
+    straight_line(
+        &robot.state,
+        &position,
+        &(ball.position - position).orientation(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geom::Segment;
+    use crate::world::{Ball, BallFriction, Field, GameState, Team, TeamInfo};
+
+    fn world(ball: Point, velocity: Vector, defending_positive_side: bool) -> World {
+        let team_info = TeamInfo {
+            name: String::new(),
+            is_blue: true,
+            score: 0,
+            goalie_id: 0,
+            defending_positive_side,
+        };
+        let mut field = Field::ssl_div_b();
+        field.defending_positive_side = defending_positive_side;
+        World {
+            field,
+            ball: Ball {
+                position: ball,
+                velocity,
+                friction: BallFriction {
+                    sliding_deceleration: 3.0,
+                    rolling_deceleration: 0.5,
+                    rolling_speed: 2.0,
+                },
+            },
+            friendly_team: Team::new().build(),
+            enemy_team: Team::new().build(),
+            game_state: GameState::new(),
+            friendly_team_info: team_info.clone(),
+            enemy_team_info: team_info,
+        }
+    }
+
+    #[test]
+    fn crossing_is_on_near_side_of_circle() {
+        let center = Point::new();
+        let origin = Point { x: 3.0, y: 0.5 };
+        let direction = Vector { x: -1.0, y: 0.0 };
+        let p = first_circle_crossing(&center, 1.0, &origin, &direction).unwrap();
+        assert!(p.x > 0.0);
+        assert!(((p - center).length() - 1.0).abs() < 1.0e-6);
+
+        let away = Vector { x: 1.0, y: 0.0 };
+        assert!(first_circle_crossing(&center, 1.0, &origin, &away).is_none());
+    }
+
+    #[test]
+    fn goalie_blocks_shot_on_goal() {
+        for defending_positive_side in [false, true] {
+            let side = if defending_positive_side { 1.0 } else { -1.0 };
+            let ball = Point {
+                x: side * 2.0,
+                y: 1.0,
+            };
+            let aim = Point {
+                x: side * 4.5,
+                y: 0.3,
+            };
+            let world = world(ball, (aim - ball).norm(5.0), defending_positive_side);
+            let crossing = predicted_goal_line_crossing(&world).unwrap();
+            assert!((crossing - aim).length() < 1.0e-6);
+
+            let target = block_position(&world, 0.09);
+            assert!(Segment::new(ball, crossing).dist_to_point(&target) < 1.0e-6);
+            assert!(world.field.friendly_defense_area().contains(&target));
+        }
+    }
+
+    #[test]
+    fn goalie_stays_in_front_of_goal_when_ball_is_behind_it() {
+        for defending_positive_side in [false, true] {
+            let side = if defending_positive_side { 1.0 } else { -1.0 };
+            let ball = Point {
+                x: side * 4.6,
+                y: 0.5,
+            };
+            let world = world(ball, Vector::new(), defending_positive_side);
+            let goal = world.field.friendly_goal_center();
+            let target = block_position(&world, 0.09);
+            assert!((target.x - goal.x) * side <= 1.0e-6);
+            assert!(target.y > 0.0);
+        }
+    }
+
+    #[test]
+    fn clearance_goes_away_from_our_goal() {
+        for defending_positive_side in [false, true] {
+            let side = if defending_positive_side { 1.0 } else { -1.0 };
+            let ball = Point {
+                x: side * 4.0,
+                y: 0.2,
+            };
+            let world = world(ball, Vector::new(), defending_positive_side);
+            assert!((clear_target(&world).x - ball.x) * side < 0.0);
+        }
+    }
+}
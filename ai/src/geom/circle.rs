@@ -1,4 +1,4 @@
-use super::Point;
+use super::{Point, Segment};
 use crate::geom::Angle;
 
 #[derive(Clone, Copy, Debug)]
@@ -30,4 +30,24 @@ impl Circle {
                 .norm(dist_to_tangent_points);
         Option::Some((p1, p2))
     }
+
+    pub fn contains(&self, p: &Point) -> bool {
+        (p - &self.center).length() <= self.radius
+    }
+
+    pub fn closest_point_on_boundary(&self, p: &Point) -> Point {
+        let center_to_point = p - &self.center;
+        if center_to_point.length() < 1.0e-9 {
+            // Every point on the boundary is equally close, so pick one arbitrarily
+            return Point {
+                x: self.center.x + self.radius,
+                y: self.center.y,
+            };
+        }
+        self.center + center_to_point.norm(self.radius)
+    }
+
+    pub fn intersects_segment(&self, segment: &Segment) -> bool {
+        segment.dist_to_point(&self.center) <= self.radius
+    }
 }
@@ -2,6 +2,7 @@ mod angle;
 mod circle;
 mod point;
 mod rectangle;
+mod segment;
 mod vector;
 
 // https://docs.rs/geo/latest/geo/index.html
@@ -10,4 +11,5 @@ pub use self::angle::Angle;
 pub use self::circle::Circle;
 pub use self::point::Point;
 pub use self::rectangle::Rectangle;
+pub use self::segment::Segment;
 pub use self::vector::Vector;
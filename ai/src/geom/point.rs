@@ -1,7 +1,7 @@
 use super::Vector;
 use std::ops::{Add, Sub};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Point {
     pub x: f64,
     pub y: f64,
@@ -1,4 +1,5 @@
 use super::point::Point;
+use super::Segment;
 
 #[derive(Clone, Copy, Debug)]
 pub struct Rectangle {
@@ -34,4 +35,123 @@ impl Rectangle {
             y: self.bottom_left.y + self.len_y() / 2.0,
         }
     }
+
+    pub fn bottom_left(&self) -> Point {
+        self.bottom_left
+    }
+
+    pub fn top_right(&self) -> Point {
+        self.top_right
+    }
+
+    pub fn contains(&self, p: &Point) -> bool {
+        self.bottom_left.x <= p.x
+            && p.x <= self.top_right.x
+            && self.bottom_left.y <= p.y
+            && p.y <= self.top_right.y
+    }
+
+    // Grows the rectangle by the margin on every side. Negative margins shrink it
+    pub fn expand(&self, margin: f64) -> Rectangle {
+        Rectangle::new(
+            Point {
+                x: self.bottom_left.x - margin,
+                y: self.bottom_left.y - margin,
+            },
+            Point {
+                x: self.top_right.x + margin,
+                y: self.top_right.y + margin,
+            },
+        )
+    }
+
+    pub fn corners(&self) -> [Point; 4] {
+        [
+            self.bottom_left,
+            Point {
+                x: self.top_right.x,
+                y: self.bottom_left.y,
+            },
+            self.top_right,
+            Point {
+                x: self.bottom_left.x,
+                y: self.top_right.y,
+            },
+        ]
+    }
+
+    // The closest point inside (or on the edge of) the rectangle
+    pub fn clamp(&self, p: &Point) -> Point {
+        Point {
+            x: p.x.clamp(self.bottom_left.x, self.top_right.x),
+            y: p.y.clamp(self.bottom_left.y, self.top_right.y),
+        }
+    }
+
+    pub fn closest_point_on_boundary(&self, p: &Point) -> Point {
+        if !self.contains(p) {
+            return self.clamp(p);
+        }
+        let dist_to_left = p.x - self.bottom_left.x;
+        let dist_to_right = self.top_right.x - p.x;
+        let dist_to_bottom = p.y - self.bottom_left.y;
+        let dist_to_top = self.top_right.y - p.y;
+        let min_dist = dist_to_left
+            .min(dist_to_right)
+            .min(dist_to_bottom)
+            .min(dist_to_top);
+        if min_dist == dist_to_left {
+            Point {
+                x: self.bottom_left.x,
+                y: p.y,
+            }
+        } else if min_dist == dist_to_right {
+            Point {
+                x: self.top_right.x,
+                y: p.y,
+            }
+        } else if min_dist == dist_to_bottom {
+            Point {
+                x: p.x,
+                y: self.bottom_left.y,
+            }
+        } else {
+            Point {
+                x: p.x,
+                y: self.top_right.y,
+            }
+        }
+    }
+
+    pub fn intersects_segment(&self, segment: &Segment) -> bool {
+        // Liang-Barsky clipping. The segment intersects if any part of it
+        // survives being clipped to the rectangle
+        let direction = segment.end - segment.start;
+        let mut t_min: f64 = 0.0;
+        let mut t_max: f64 = 1.0;
+        let checks = [
+            (-direction.x, segment.start.x - self.bottom_left.x),
+            (direction.x, self.top_right.x - segment.start.x),
+            (-direction.y, segment.start.y - self.bottom_left.y),
+            (direction.y, self.top_right.y - segment.start.y),
+        ];
+        for (p, q) in checks {
+            if p.abs() < 1.0e-9 {
+                if q < 0.0 {
+                    return false;
+                }
+            } else {
+                let t = q / p;
+                if p < 0.0 {
+                    t_min = t_min.max(t);
+                } else {
+                    t_max = t_max.min(t);
+                }
+                if t_min > t_max {
+                    return false;
+                }
+            }
+        }
+        true
+    }
 }
@@ -0,0 +1,84 @@
+use super::Point;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Segment {
+    pub start: Point,
+    pub end: Point,
+}
+
+impl Segment {
+    pub fn new(start: Point, end: Point) -> Segment {
+        Segment { start, end }
+    }
+
+    pub fn length(&self) -> f64 {
+        (self.end - self.start).length()
+    }
+
+    pub fn closest_point(&self, p: &Point) -> Point {
+        let direction = self.end - self.start;
+        let length_squared = direction.dot(&direction);
+        if length_squared < 1.0e-9 {
+            return self.start;
+        }
+        let t = ((p - &self.start).dot(&direction) / length_squared).clamp(0.0, 1.0);
+        self.start + direction * t
+    }
+
+    pub fn dist_to_point(&self, p: &Point) -> f64 {
+        (self.closest_point(p) - p).length()
+    }
+
+    pub fn intersects(&self, other: &Segment) -> bool {
+        // Which side of the line through a-b the point is on
+        let side = |a: &Point, b: &Point, p: &Point| -> f64 {
+            let ab = b - a;
+            let ap = p - a;
+            ab.x * ap.y - ab.y * ap.x
+        };
+        let d1 = side(&other.start, &other.end, &self.start);
+        let d2 = side(&other.start, &other.end, &self.end);
+        let d3 = side(&self.start, &self.end, &other.start);
+        let d4 = side(&self.start, &self.end, &other.end);
+        if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+            return true;
+        }
+        // Touching or collinear segments
+        const EPSILON: f64 = 1.0e-9;
+        (d1.abs() < EPSILON && self.dist_to_point(&other.start) < EPSILON)
+            || (d2.abs() < EPSILON && self.dist_to_point(&other.end) < EPSILON)
+            || (d3.abs() < EPSILON && other.dist_to_point(&self.start) < EPSILON)
+            || (d4.abs() < EPSILON && other.dist_to_point(&self.end) < EPSILON)
+    }
+
+    pub fn dist_to_segment(&self, other: &Segment) -> f64 {
+        if self.intersects(other) {
+            return 0.0;
+        }
+        self.dist_to_point(&other.start)
+            .min(self.dist_to_point(&other.end))
+            .min(other.dist_to_point(&self.start))
+            .min(other.dist_to_point(&self.end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crossing_segments_have_zero_distance() {
+        let a = Segment::new(Point { x: -1.0, y: 0.0 }, Point { x: 1.0, y: 0.0 });
+        let b = Segment::new(Point { x: 0.0, y: -1.0 }, Point { x: 0.0, y: 1.0 });
+        assert!(a.intersects(&b));
+        assert_eq!(a.dist_to_segment(&b), 0.0);
+    }
+
+    #[test]
+    fn distance_between_parallel_segments() {
+        let a = Segment::new(Point { x: 0.0, y: 0.0 }, Point { x: 2.0, y: 0.0 });
+        let b = Segment::new(Point { x: 1.0, y: 0.5 }, Point { x: 3.0, y: 0.5 });
+        assert!(!a.intersects(&b));
+        assert!((a.dist_to_segment(&b) - 0.5).abs() < 1.0e-9);
+    }
+}
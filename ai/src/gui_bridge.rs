@@ -1,12 +1,15 @@
 use crate::communication::buffer::{NodeReceiver, NodeSender};
 use crate::communication::node::Node;
+use crate::gameplay::evaluation::heatmap::Heatmap;
 use crate::motion::{bb_time_to_position, Trajectory};
 use crate::proto;
 use crate::proto::config;
 use crate::proto::config::Config;
 use crate::proto::ssl_simulation::{SimulatorCommand, SimulatorControl};
 use crate::proto::ssl_vision::SslWrapperPackets;
-use crate::proto_conversions::{node_performance_to_proto, trajectories_to_proto, world_to_proto};
+use crate::proto_conversions::{
+    heatmaps_to_proto, node_performance_to_proto, trajectories_to_proto, world_to_proto,
+};
 use crate::world::{Ball, Field, Robot, World};
 use prost::Message;
 use proto::metrics::NodePerformance;
@@ -24,6 +27,7 @@ pub struct Input {
     pub world: NodeReceiver<World>,
     pub trajectories: NodeReceiver<HashMap<usize, Trajectory>>,
     pub metrics: NodeReceiver<(String, f64)>,
+    pub heatmaps: NodeReceiver<Vec<Arc<Heatmap>>>,
 }
 pub struct Output {
     pub sim_control: NodeSender<SimulatorControl>,
@@ -108,6 +112,19 @@ impl Node for GuiBridge {
             );
         }
 
+        if let Some(heatmaps) = self.input.heatmaps.take_last()? {
+            let msg = heatmaps_to_proto(&heatmaps);
+            self.publish_msg(
+                msg,
+                self.config
+                    .lock()
+                    .unwrap()
+                    .gui_bridge
+                    .heatmaps_topic
+                    .to_string(),
+            );
+        }
+
         let mut node_performance = HashMap::<String, f64>::new();
         for (topic, pub_period_ms) in self.input.metrics.dump()? {
             if !node_performance.contains_key(&topic) {
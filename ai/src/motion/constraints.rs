@@ -0,0 +1,383 @@
+use crate::geom::{Angle, Circle, Point, Rectangle, Segment, Vector};
+use crate::motion::{KinematicState, Trajectory};
+use crate::proto::config::Rules;
+use crate::world::{Ball, Field, GameState};
+
+// Extra distance kept from the edge of forbidden regions, so small tracking
+// errors don't immediately put the robot back inside
+const CLEARANCE: f64 = 0.05;
+// Moving a point out of one region can move it into another (eg. the ball is
+// right next to the defense area), so we re-check a limited number of times
+const MAX_CORRECTION_ITERATIONS: usize = 5;
+const NUM_CIRCLE_DETOUR_POINTS: usize = 8;
+
+#[derive(Clone, Debug)]
+pub enum Region {
+    Circle(Circle),
+    Rectangle(Rectangle),
+    // Everything within the distance of the segment
+    Corridor((Segment, f64)),
+}
+
+impl Region {
+    pub fn contains(&self, p: &Point) -> bool {
+        match self {
+            Self::Circle(c) => c.contains(p),
+            Self::Rectangle(r) => r.contains(p),
+            Self::Corridor((s, width)) => s.dist_to_point(p) <= *width,
+        }
+    }
+
+    pub fn intersects_segment(&self, segment: &Segment) -> bool {
+        match self {
+            Self::Circle(c) => c.intersects_segment(segment),
+            Self::Rectangle(r) => r.intersects_segment(segment),
+            Self::Corridor((s, width)) => s.dist_to_segment(segment) <= *width,
+        }
+    }
+
+    fn closest_point_outside(&self, p: &Point) -> Point {
+        match self {
+            Self::Circle(c) => Circle {
+                center: c.center,
+                radius: c.radius + CLEARANCE,
+            }
+            .closest_point_on_boundary(p),
+            Self::Rectangle(r) => r.expand(CLEARANCE).closest_point_on_boundary(p),
+            Self::Corridor((s, width)) => {
+                let closest = s.closest_point(p);
+                let away = p - &closest;
+                let away = if away.length() > 1.0e-6 {
+                    away
+                } else {
+                    (s.end - s.start).perpendicular()
+                };
+                closest + away.norm(width + CLEARANCE)
+            }
+        }
+    }
+
+    // Candidate waypoints for driving around the region
+    fn detour_points(&self) -> Vec<Point> {
+        match self {
+            Self::Circle(c) => {
+                // The vertices of a regular polygon enclosing the circle
+                let vertex_dist = (c.radius + CLEARANCE)
+                    / (std::f64::consts::PI / NUM_CIRCLE_DETOUR_POINTS as f64).cos();
+                (0..NUM_CIRCLE_DETOUR_POINTS)
+                    .map(|i| {
+                        c.center
+                            + Vector::from_angle(
+                                Angle::full() / NUM_CIRCLE_DETOUR_POINTS * i,
+                                vertex_dist,
+                            )
+                    })
+                    .collect()
+            }
+            Self::Rectangle(r) => r.expand(CLEARANCE).corners().to_vec(),
+            Self::Corridor((s, width)) => {
+                // The corners of a rectangle around the corridor
+                let along = if s.length() > 1.0e-6 {
+                    (s.end - s.start).norm(width + CLEARANCE)
+                } else {
+                    Vector {
+                        x: width + CLEARANCE,
+                        y: 0.0,
+                    }
+                };
+                let across = along.perpendicular();
+                vec![
+                    s.start + (-along) + across,
+                    s.start + (-along) + (-across),
+                    s.end + along + across,
+                    s.end + along + (-across),
+                ]
+            }
+        }
+    }
+}
+
+// The restrictions the rules place on a single robot's motion for the current
+// game state. All distances are to the robot's center, so they include the robot radius
+#[derive(Clone, Debug)]
+pub struct MotionConstraints {
+    pub max_speed: Option<f64>,
+    pub avoid: Vec<Region>,
+    pub stay_within: Vec<Rectangle>,
+}
+
+impl MotionConstraints {
+    pub fn new() -> MotionConstraints {
+        MotionConstraints {
+            max_speed: None,
+            avoid: vec![],
+            stay_within: vec![],
+        }
+    }
+
+    pub fn from_game_state(
+        is_goalie: bool,
+        is_kicker: bool,
+        game_state: &GameState,
+        field: &Field,
+        ball: &Ball,
+        rules: &Rules,
+    ) -> MotionConstraints {
+        let robot_radius = rules.robot_max_radius;
+        let mut constraints = MotionConstraints::new();
+
+        if game_state.stopped() {
+            constraints.max_speed = Some(rules.stop_max_robot_speed);
+        }
+
+        if game_state.friendly_stay_away_from_ball() {
+            constraints.avoid.push(Region::Circle(Circle {
+                center: ball.position,
+                radius: rules.stop_min_dist_to_ball + robot_radius,
+            }));
+        }
+        if let (true, Some(target)) = (
+            game_state.their_ball_placement(),
+            game_state.ball_placement_position(),
+        ) {
+            constraints.avoid.push(Region::Corridor((
+                Segment::new(ball.position, target),
+                rules.stop_min_dist_to_ball + robot_radius,
+            )));
+        }
+
+        if !is_goalie {
+            constraints.avoid.push(Region::Rectangle(
+                field.friendly_defense_area().expand(robot_radius),
+            ));
+        }
+        let enemy_defense_area_margin = if game_state.playing() {
+            robot_radius
+        } else {
+            robot_radius + rules.stop_min_dist_to_enemy_defense_area
+        };
+        constraints.avoid.push(Region::Rectangle(
+            field.enemy_defense_area().expand(enemy_defense_area_margin),
+        ));
+
+        let max_x = field.x_length / 2.0 + field.boundary_size;
+        let max_y = field.y_length / 2.0 + field.boundary_size;
+        let side = field.friendly_side();
+        if game_state.stay_on_side() && !(is_kicker && game_state.our_kickoff()) {
+            constraints.stay_within.push(Rectangle::new(
+                Point {
+                    x: side * max_x,
+                    y: -max_y,
+                },
+                Point {
+                    x: side * robot_radius,
+                    y: max_y,
+                },
+            ));
+            constraints.avoid.push(Region::Circle(Circle {
+                center: Point::new(),
+                radius: field.center_circle_radius + robot_radius,
+            }));
+        }
+
+        // The kicker and the defending goalie are the only robots allowed in front of
+        // the ball for a penalty
+        if game_state.stay_behind_penalty_line()
+            && !(is_goalie && game_state.their_penalty())
+            && !(is_kicker && game_state.our_penalty())
+        {
+            let dist_behind_ball = rules.penalty_min_dist_behind_ball + robot_radius;
+            // Behind the ball is towards the goal of the team taking the penalty
+            let kicking_side = if game_state.our_penalty() {
+                side
+            } else {
+                -side
+            };
+            constraints.stay_within.push(Rectangle::new(
+                Point {
+                    x: kicking_side * max_x,
+                    y: -max_y,
+                },
+                Point {
+                    x: ball.position.x + kicking_side * dist_behind_ball,
+                    y: max_y,
+                },
+            ));
+        }
+
+        constraints
+    }
+
+    pub fn is_valid(&self, p: &Point) -> bool {
+        self.stay_within.iter().all(|r| r.contains(p)) && !self.avoid.iter().any(|r| r.contains(p))
+    }
+
+    // Moves the point the shortest distance required to satisfy the constraints.
+    // This is best-effort, since some combinations of constraints can't be satisfied
+    pub fn closest_valid_point(&self, p: &Point) -> Point {
+        let mut result = *p;
+        for _ in 0..MAX_CORRECTION_ITERATIONS {
+            if self.is_valid(&result) {
+                break;
+            }
+            for r in &self.stay_within {
+                result = r.clamp(&result);
+            }
+            for r in &self.avoid {
+                if r.contains(&result) {
+                    result = r.closest_point_outside(&result);
+                }
+            }
+        }
+        result
+    }
+
+    // Returns the points to drive through to get from start to end without entering
+    // any forbidden region, including the end point but not the start
+    fn route(&self, start: Point, end: Point) -> Vec<Point> {
+        let segment = Segment::new(start, end);
+        let blocking_region = self
+            .avoid
+            .iter()
+            .find(|r| r.intersects_segment(&segment) && !r.contains(&start) && !r.contains(&end));
+        let region = match blocking_region {
+            Some(r) => r,
+            None => return vec![end],
+        };
+        let path_length = |w: &Point| (w - &start).length() + (end - w).length();
+        let detour = region
+            .detour_points()
+            .iter()
+            .map(|w| self.closest_valid_point(w))
+            .filter(|w| {
+                !region.intersects_segment(&Segment::new(start, *w))
+                    && !region.intersects_segment(&Segment::new(*w, end))
+            })
+            .min_by(|a, b| path_length(a).total_cmp(&path_length(b)));
+        match detour {
+            Some(w) => vec![w, end],
+            None => vec![end],
+        }
+    }
+
+    pub fn apply(&self, initial_state: &KinematicState, trajectory: Trajectory) -> Trajectory {
+        if trajectory.points.is_empty() {
+            return trajectory;
+        }
+
+        let mut waypoints = vec![initial_state.position];
+        // If we're already somewhere we shouldn't be, leave by the shortest path first
+        let exit_point = self.closest_valid_point(&initial_state.position);
+        if (exit_point - initial_state.position).length() > 1.0e-6 {
+            waypoints.push(exit_point);
+        }
+        for p in trajectory.points.iter().skip(1) {
+            waypoints.push(self.closest_valid_point(p));
+        }
+
+        let mut points = vec![waypoints[0]];
+        for w in waypoints.windows(2) {
+            points.extend(self.route(w[0], w[1]));
+        }
+
+        let max_speed = match (trajectory.max_speed, self.max_speed) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        Trajectory {
+            points,
+            max_speed,
+            ..trajectory
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::ssl_gamecontroller::referee::Command;
+    use crate::world::BallFriction;
+
+    fn rules() -> Rules {
+        Rules {
+            robot_max_radius: 0.09,
+            stop_min_dist_to_ball: 0.5,
+            stop_max_robot_speed: 1.5,
+            stop_min_dist_to_enemy_defense_area: 0.2,
+            ..Default::default()
+        }
+    }
+
+    fn ball_at(position: Point) -> Ball {
+        Ball {
+            position,
+            velocity: Vector::new(),
+            friction: BallFriction {
+                sliding_deceleration: 3.0,
+                rolling_deceleration: 0.5,
+                rolling_speed: 2.0,
+            },
+        }
+    }
+
+    fn stopped_game() -> GameState {
+        let mut game_state = GameState::new();
+        game_state.update_command(Command::Stop, true);
+        game_state
+    }
+
+    #[test]
+    fn robots_keep_away_from_ball_during_stop() {
+        let ball = ball_at(Point { x: 1.0, y: 1.0 });
+        let constraints = MotionConstraints::from_game_state(
+            false,
+            false,
+            &stopped_game(),
+            &Field::ssl_div_b(),
+            &ball,
+            &rules(),
+        );
+        assert_eq!(constraints.max_speed, Some(1.5));
+        for p in [
+            Point { x: 1.1, y: 1.0 },
+            Point { x: 0.7, y: 0.8 },
+            ball.position,
+        ] {
+            let valid = constraints.closest_valid_point(&p);
+            assert!(constraints.is_valid(&valid));
+            assert!((valid - ball.position).length() >= 0.5);
+        }
+    }
+
+    #[test]
+    fn robots_stay_out_of_both_defense_areas() {
+        let field = Field::ssl_div_b();
+        let ball = ball_at(Point::new());
+        let constraints = MotionConstraints::from_game_state(
+            false,
+            false,
+            &stopped_game(),
+            &field,
+            &ball,
+            &rules(),
+        );
+        for p in [field.friendly_goal_center(), field.enemy_goal_center()] {
+            let inside = p + (Point::new() - p).norm(0.5);
+            assert!(!constraints.is_valid(&inside));
+            let valid = constraints.closest_valid_point(&inside);
+            assert!(!field.friendly_defense_area().expand(0.09).contains(&valid));
+            assert!(!field.enemy_defense_area().expand(0.29).contains(&valid));
+        }
+
+        // Only the goalie may go into our defense area
+        let goalie_constraints = MotionConstraints::from_game_state(
+            true,
+            false,
+            &stopped_game(),
+            &field,
+            &ball,
+            &rules(),
+        );
+        assert!(goalie_constraints.is_valid(&field.friendly_goal_center()));
+    }
+}
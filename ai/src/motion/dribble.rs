@@ -0,0 +1,325 @@
+use crate::geom::{Angle, Point, Vector};
+use crate::motion::intercept::earliest_intercept;
+use crate::motion::planner::straight_line;
+use crate::motion::Trajectory;
+use crate::world::{Ball, Robot};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// Distance from the robot's center to the ball's center when it's on the dribbler
+pub const DRIBBLER_BALL_DIST: f64 = 0.1;
+// How far from the dribbler the ball can be while we still consider it captured.
+// A bit generous since vision loses track of the ball when it's tucked in front of the robot
+const POSSESSION_DIST_TOLERANCE: f64 = 0.04;
+const POSSESSION_MAX_ANGLE_DEG: f64 = 25.0;
+// Losing the ball for less than this, without it going anywhere, doesn't count as
+// giving up possession
+const POSSESSION_LOSS_GRACE: Duration = Duration::from_millis(300);
+const POSSESSION_LOSS_MAX_BALL_DIST: f64 = 0.3;
+
+// Where the robot lines up before driving onto the ball
+pub const STAGING_DIST: f64 = 0.25;
+const STAGING_TOLERANCE: f64 = 0.05;
+const STAGING_MAX_ANGLE_ERROR_DEG: f64 = 10.0;
+// Faster than this the ball has to be intercepted rather than approached from behind
+const MOVING_BALL_SPEED: f64 = 0.5;
+const INTERCEPT_MAX_ACCELERATION: f64 = 3.0;
+const INTERCEPT_MAX_SPEED: f64 = 3.0;
+
+pub const CAPTURE_MAX_SPEED: f64 = 0.5;
+// Conservative limits so the ball stays on the dribbler
+const DRIBBLE_MAX_SPEED: f64 = 1.0;
+const DRIBBLE_MAX_ACCELERATION: f64 = 1.0;
+const DRIBBLE_MAX_ANGULAR_SPEED_DEG: f64 = 90.0;
+// Stop a little short of the max dribble distance to allow for tracking error
+const DRIBBLE_DISTANCE_MARGIN: f64 = 0.15;
+// Extra distance to back away from a placed ball, so the ball rolling slightly
+// doesn't bring it too close again
+const BACK_OFF_MARGIN: f64 = 0.05;
+
+pub fn has_ball(robot: &Robot, ball: &Ball) -> bool {
+    let robot_to_ball = ball.position - robot.state.position;
+    if robot_to_ball.length() > DRIBBLER_BALL_DIST + POSSESSION_DIST_TOLERANCE {
+        return false;
+    }
+    let angle_error = (robot_to_ball.orientation() - robot.state.orientation).clamp_pos_neg_pi();
+    angle_error.degrees().abs() < POSSESSION_MAX_ANGLE_DEG
+}
+
+struct Capture {
+    position: Point,
+    last_possession_time: Instant,
+}
+
+// Remembers where each robot gained possession of the ball, since the rules limit
+// how far the ball can be dribbled from that point
+pub struct DribbleTracker {
+    captures: HashMap<usize, Capture>,
+}
+
+impl DribbleTracker {
+    pub fn new() -> DribbleTracker {
+        DribbleTracker {
+            captures: HashMap::new(),
+        }
+    }
+
+    pub fn update(&mut self, robots: &Vec<&Robot>, ball: &Ball, time: Instant) {
+        let mut captures = HashMap::new();
+        for r in robots {
+            let previous = self.captures.remove(&r.id);
+            if has_ball(r, ball) {
+                let position = previous.map_or(ball.position, |c| c.position);
+                captures.insert(
+                    r.id,
+                    Capture {
+                        position,
+                        last_possession_time: time,
+                    },
+                );
+            } else if let Some(c) = previous {
+                // Vision often loses the ball on the dribbler for a moment, which doesn't
+                // mean we've let go of it
+                let recently_had_ball =
+                    time.saturating_duration_since(c.last_possession_time) < POSSESSION_LOSS_GRACE;
+                let ball_nearby =
+                    (ball.position - r.state.position).length() < POSSESSION_LOSS_MAX_BALL_DIST;
+                if recently_had_ball && ball_nearby {
+                    captures.insert(r.id, c);
+                }
+            }
+        }
+        self.captures = captures;
+    }
+
+    pub fn capture_position(&self, id: usize) -> Option<Point> {
+        self.captures.get(&id).map(|c| c.position)
+    }
+
+    pub fn distance_dribbled(&self, id: usize, ball: &Ball) -> Option<f64> {
+        self.capture_position(id)
+            .map(|p| (ball.position - p).length())
+    }
+}
+
+fn dribbling_trajectory(
+    robot: &Robot,
+    target_position: &Point,
+    target_orientation: &Angle,
+) -> Trajectory {
+    let mut traj = straight_line(&robot.state, target_position, target_orientation);
+    traj.dribble = true;
+    traj.max_speed = Some(DRIBBLE_MAX_SPEED);
+    traj.max_acceleration = Some(DRIBBLE_MAX_ACCELERATION);
+    traj.max_angular_speed = Some(Angle::from_degrees(DRIBBLE_MAX_ANGULAR_SPEED_DEG));
+    traj
+}
+
+// Get in front of a moving ball facing it, and let it roll onto the dribbler
+pub fn intercept_trajectory(robot: &Robot, ball: &Ball) -> Trajectory {
+    let intercept =
+        earliest_intercept(robot, ball, INTERCEPT_MAX_ACCELERATION, INTERCEPT_MAX_SPEED);
+    let position = intercept.position + ball.velocity.norm(DRIBBLER_BALL_DIST);
+    let mut traj = straight_line(&robot.state, &position, &(-ball.velocity).orientation());
+    traj.dribble = true;
+    traj
+}
+
+pub fn is_ball_moving(ball: &Ball) -> bool {
+    ball.velocity.length() > MOVING_BALL_SPEED
+}
+
+// Moves the robot to a point behind the ball so it can drive straight onto it facing
+// the given orientation. Returns None once the robot is lined up
+pub fn staging_trajectory(robot: &Robot, ball: &Ball, orientation: &Angle) -> Option<Trajectory> {
+    let robot_to_ball = ball.position - robot.state.position;
+    let lined_up = (robot_to_ball.orientation() - *orientation)
+        .clamp_pos_neg_pi()
+        .degrees()
+        .abs()
+        < STAGING_MAX_ANGLE_ERROR_DEG
+        && robot_to_ball.length() < STAGING_DIST + STAGING_TOLERANCE;
+    if lined_up {
+        return None;
+    }
+    let staging_position = ball.position + Vector::from_angle(*orientation, -STAGING_DIST);
+    Some(straight_line(&robot.state, &staging_position, orientation))
+}
+
+fn capture_trajectory(robot: &Robot, ball: &Ball, target: &Point) -> Trajectory {
+    if is_ball_moving(ball) {
+        return intercept_trajectory(robot, ball);
+    }
+
+    // Approach from the side opposite the target, so we don't have to turn with the ball
+    let ball_to_target = target - &ball.position;
+    let approach_orientation = if ball_to_target.length() > 1.0e-3 {
+        ball_to_target.orientation()
+    } else {
+        (ball.position - robot.state.position).orientation()
+    };
+    if let Some(traj) = staging_trajectory(robot, ball, &approach_orientation) {
+        return traj;
+    }
+
+    let capture_position =
+        ball.position + Vector::from_angle(approach_orientation, -DRIBBLER_BALL_DIST);
+    let mut traj = straight_line(&robot.state, &capture_position, &approach_orientation);
+    traj.dribble = true;
+    traj.max_speed = Some(CAPTURE_MAX_SPEED);
+    traj
+}
+
+// Gets the ball and dribbles it so it ends up at the target, with the robot facing the
+// target orientation. Once captured, the ball is never taken further than the max dribble
+// distance from where it was captured, so the robot stops short of far away targets
+pub fn dribble_to(
+    robot: &Robot,
+    ball: &Ball,
+    target: &Point,
+    target_orientation: &Angle,
+    capture_position: Option<Point>,
+    max_dribble_distance: f64,
+) -> Trajectory {
+    if !has_ball(robot, ball) {
+        return capture_trajectory(robot, ball, target);
+    }
+
+    let capture_position = capture_position.unwrap_or(ball.position);
+    let max_distance = (max_dribble_distance - DRIBBLE_DISTANCE_MARGIN).max(0.0);
+    let capture_to_target = target - &capture_position;
+    let ball_target = if capture_to_target.length() > max_distance {
+        capture_position + capture_to_target.norm(max_distance)
+    } else {
+        *target
+    };
+    let robot_target = ball_target + Vector::from_angle(*target_orientation, -DRIBBLER_BALL_DIST);
+    dribbling_trajectory(robot, &robot_target, target_orientation)
+}
+
+// Carries the ball to the target and then backs away from it. Unlike dribbling during
+// the game, there's no limit on how far the ball can be carried while placing it
+pub fn place_ball(
+    robot: &Robot,
+    ball: &Ball,
+    target: &Point,
+    tolerance: f64,
+    min_dist_to_ball: f64,
+) -> Trajectory {
+    let ball_to_target = target - &ball.position;
+    if ball_to_target.length() < tolerance && !is_ball_moving(ball) {
+        let away_from_ball = robot.state.position - ball.position;
+        let away_from_ball = if away_from_ball.length() > 1.0e-6 {
+            away_from_ball
+        } else {
+            Vector::from_angle(robot.state.orientation, -1.0)
+        };
+        let position = ball.position + away_from_ball.norm(min_dist_to_ball + BACK_OFF_MARGIN);
+        let mut traj = straight_line(&robot.state, &position, &(-away_from_ball).orientation());
+        traj.max_speed = Some(CAPTURE_MAX_SPEED);
+        return traj;
+    }
+
+    let orientation = if ball_to_target.length() > 1.0e-3 {
+        ball_to_target.orientation()
+    } else {
+        robot.state.orientation
+    };
+    dribble_to(robot, ball, target, &orientation, None, f64::INFINITY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::motion::KinematicState;
+    use crate::world::BallFriction;
+
+    fn robot_at(position: Point, orientation: Angle) -> Robot {
+        Robot {
+            id: 0,
+            state: KinematicState {
+                position,
+                orientation,
+                velocity: Vector::new(),
+                angular_velocity: Angle::zero(),
+            },
+        }
+    }
+
+    fn stationary_ball(position: Point) -> Ball {
+        Ball {
+            position,
+            velocity: Vector::new(),
+            friction: BallFriction {
+                sliding_deceleration: 3.0,
+                rolling_deceleration: 0.5,
+                rolling_speed: 2.0,
+            },
+        }
+    }
+
+    #[test]
+    fn dribble_stops_before_max_distance() {
+        let robot = robot_at(Point::new(), Angle::zero());
+        let ball = stationary_ball(Point {
+            x: DRIBBLER_BALL_DIST,
+            y: 0.0,
+        });
+        let target = Point { x: 5.0, y: 0.0 };
+        let traj = dribble_to(
+            &robot,
+            &ball,
+            &target,
+            &Angle::zero(),
+            Some(ball.position),
+            1.0,
+        );
+        assert!(traj.dribble);
+        let ball_end = traj.points.last().unwrap().x + DRIBBLER_BALL_DIST;
+        assert!(ball_end - ball.position.x < 1.0);
+    }
+
+    #[test]
+    fn capture_position_survives_single_frame_dropout() {
+        let mut tracker = DribbleTracker::new();
+        let start = Instant::now();
+        let frame = Duration::from_millis(16);
+        let capture = Point {
+            x: DRIBBLER_BALL_DIST,
+            y: 0.0,
+        };
+        let robot = robot_at(Point::new(), Angle::zero());
+        tracker.update(&vec![&robot], &stationary_ball(capture), start);
+
+        // The ball isn't seen on the dribbler for one frame while the robot carries it
+        let robot = robot_at(Point { x: 0.2, y: 0.0 }, Angle::zero());
+        tracker.update(&vec![&robot], &stationary_ball(capture), start + frame);
+        assert_eq!(tracker.capture_position(0), Some(capture));
+
+        let ball = stationary_ball(Point {
+            x: 0.3 + DRIBBLER_BALL_DIST,
+            y: 0.0,
+        });
+        let robot = robot_at(Point { x: 0.3, y: 0.0 }, Angle::zero());
+        tracker.update(&vec![&robot], &ball, start + frame * 2);
+        assert_eq!(tracker.capture_position(0), Some(capture));
+        assert!((tracker.distance_dribbled(0, &ball).unwrap() - 0.3).abs() < 1.0e-9);
+
+        // Once the ball has really gone, the next capture starts over
+        let far_ball = stationary_ball(Point { x: 2.0, y: 0.0 });
+        tracker.update(&vec![&robot], &far_ball, start + frame * 3);
+        assert_eq!(tracker.capture_position(0), None);
+    }
+
+    #[test]
+    fn approach_lines_up_behind_ball() {
+        let robot = robot_at(Point { x: -1.0, y: 1.0 }, Angle::zero());
+        let ball = stationary_ball(Point::new());
+        let target = Point { x: 1.0, y: 0.0 };
+        let traj = dribble_to(&robot, &ball, &target, &Angle::zero(), None, 1.0);
+        let staging_position = traj.points.last().unwrap();
+        assert!((staging_position.x + STAGING_DIST).abs() < 1.0e-6);
+        assert!(staging_position.y.abs() < 1.0e-6);
+        assert!(!traj.dribble);
+    }
+}
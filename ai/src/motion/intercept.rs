@@ -0,0 +1,140 @@
+use crate::geom::Point;
+use crate::motion::bb_time_to_position;
+use crate::world::{Ball, Robot};
+
+const SEARCH_TIME_STEP: f64 = 0.05;
+const NUM_REFINEMENT_STEPS: usize = 10;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Intercept {
+    pub position: Point,
+    // Seconds from now until the robot and ball meet
+    pub time: f64,
+}
+
+// Finds the earliest time the robot can reach the ball's predicted path before the ball
+// gets there. If the robot can't catch the ball while it's rolling, the intercept is
+// where the ball will come to rest
+pub fn earliest_intercept(
+    robot: &Robot,
+    ball: &Ball,
+    max_acceleration: f64,
+    max_speed: f64,
+) -> Intercept {
+    let time_margin = |t: f64| -> f64 {
+        let robot_time = bb_time_to_position(
+            &robot.state.position,
+            &robot.state.velocity,
+            &ball.position_at(t),
+            max_acceleration,
+            max_speed,
+        );
+        t - robot_time
+    };
+
+    let stop_time = ball.stop_time();
+    let mut prev_t = 0.0;
+    let mut t = 0.0;
+    while t <= stop_time {
+        if time_margin(t) >= 0.0 {
+            if t == 0.0 {
+                return Intercept {
+                    position: ball.position,
+                    time: 0.0,
+                };
+            }
+            // Narrow down when the robot first becomes able to get there in time
+            let (mut infeasible_t, mut feasible_t) = (prev_t, t);
+            for _ in 0..NUM_REFINEMENT_STEPS {
+                let mid_t = (infeasible_t + feasible_t) / 2.0;
+                if time_margin(mid_t) >= 0.0 {
+                    feasible_t = mid_t;
+                } else {
+                    infeasible_t = mid_t;
+                }
+            }
+            return Intercept {
+                position: ball.position_at(feasible_t),
+                time: feasible_t,
+            };
+        }
+        prev_t = t;
+        t += SEARCH_TIME_STEP;
+    }
+
+    let stop_position = ball.stop_position();
+    let robot_time = bb_time_to_position(
+        &robot.state.position,
+        &robot.state.velocity,
+        &stop_position,
+        max_acceleration,
+        max_speed,
+    );
+    Intercept {
+        position: stop_position,
+        time: robot_time.max(stop_time),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geom::{Angle, Vector};
+    use crate::motion::KinematicState;
+    use crate::world::BallFriction;
+
+    const ROLLING_DECELERATION: f64 = 0.5;
+
+    fn rolling_ball(position: Point, velocity: Vector) -> Ball {
+        Ball {
+            position,
+            velocity,
+            friction: BallFriction {
+                sliding_deceleration: 3.0,
+                rolling_deceleration: ROLLING_DECELERATION,
+                rolling_speed: 10.0,
+            },
+        }
+    }
+
+    fn stationary_robot(position: Point) -> Robot {
+        Robot {
+            id: 0,
+            state: KinematicState {
+                position,
+                orientation: Angle::zero(),
+                velocity: Vector::new(),
+                angular_velocity: Angle::zero(),
+            },
+        }
+    }
+
+    #[test]
+    fn intercept_stationary_ball() {
+        let robot = stationary_robot(Point::new());
+        let ball = rolling_ball(Point { x: 1.0, y: 0.0 }, Vector::new());
+        let result = earliest_intercept(&robot, &ball, 3.0, 3.0);
+        assert!((result.position - ball.position).length() < 1.0e-6);
+        assert!(result.time > 0.0);
+    }
+
+    #[test]
+    fn intercept_ball_rolling_towards_robot() {
+        let robot = stationary_robot(Point::new());
+        let ball = rolling_ball(Point { x: 3.0, y: 0.0 }, Vector { x: -2.0, y: 0.0 });
+        let result = earliest_intercept(&robot, &ball, 3.0, 3.0);
+        // The robot should meet the ball part way rather than waiting for it
+        assert!(result.position.x > 0.0 && result.position.x < 3.0);
+        assert!(result.time < 1.5);
+    }
+
+    #[test]
+    fn intercept_ball_stops_before_robot_arrives() {
+        let robot = stationary_robot(Point::new());
+        let ball = rolling_ball(Point { x: 1.0, y: 0.0 }, Vector { x: 4.0, y: 0.0 });
+        let result = earliest_intercept(&robot, &ball, 1.0, 1.0);
+        let stop_dist = 4.0_f64.powi(2) / (2.0 * ROLLING_DECELERATION);
+        assert!((result.position.x - (1.0 + stop_dist)).abs() < 1.0e-6);
+        assert!(result.time >= 4.0 / ROLLING_DECELERATION);
+    }
+}
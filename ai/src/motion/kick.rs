@@ -0,0 +1,89 @@
+use crate::geom::{Point, Vector};
+use crate::motion::dribble::{intercept_trajectory, is_ball_moving, staging_trajectory};
+use crate::motion::planner::straight_line;
+use crate::motion::Trajectory;
+use crate::world::{Ball, Robot};
+
+// Aim past the ball so the robot doesn't slow down before making contact
+const FOLLOW_THROUGH_DIST: f64 = 0.1;
+const KICK_APPROACH_MAX_SPEED: f64 = 1.0;
+
+// Lines up behind the ball facing the target, then drives through it. Also returns
+// whether the robot is driving through the ball, which is when the kicker should be armed
+fn strike_trajectory(robot: &Robot, ball: &Ball, target: &Point) -> (Trajectory, bool) {
+    if is_ball_moving(ball) {
+        return (intercept_trajectory(robot, ball), false);
+    }
+
+    let kick_orientation = (target - &ball.position).orientation();
+    if let Some(traj) = staging_trajectory(robot, ball, &kick_orientation) {
+        return (traj, false);
+    }
+
+    let follow_through_position =
+        ball.position + Vector::from_angle(kick_orientation, FOLLOW_THROUGH_DIST);
+    let mut traj = straight_line(&robot.state, &follow_through_position, &kick_orientation);
+    traj.max_speed = Some(KICK_APPROACH_MAX_SPEED);
+    (traj, true)
+}
+
+pub fn kick_ball(robot: &Robot, ball: &Ball, target: &Point, speed: f64) -> Trajectory {
+    let (mut traj, striking) = strike_trajectory(robot, ball, target);
+    if striking {
+        traj.autokick_speed = Some(speed);
+    }
+    traj
+}
+
+// Like kicking, but the ball goes over anything in the first part of its path
+pub fn chip_ball(robot: &Robot, ball: &Ball, target: &Point, distance: f64) -> Trajectory {
+    let (mut traj, striking) = strike_trajectory(robot, ball, target);
+    if striking {
+        traj.autochip_distance = Some(distance);
+    }
+    traj
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geom::Angle;
+    use crate::motion::KinematicState;
+    use crate::world::BallFriction;
+
+    fn robot_at(position: Point) -> Robot {
+        Robot {
+            id: 0,
+            state: KinematicState {
+                position,
+                orientation: Angle::zero(),
+                velocity: Vector::new(),
+                angular_velocity: Angle::zero(),
+            },
+        }
+    }
+
+    fn stationary_ball() -> Ball {
+        Ball {
+            position: Point::new(),
+            velocity: Vector::new(),
+            friction: BallFriction {
+                sliding_deceleration: 3.0,
+                rolling_deceleration: 0.5,
+                rolling_speed: 2.0,
+            },
+        }
+    }
+
+    #[test]
+    fn chipper_only_armed_when_lined_up() {
+        let ball = stationary_ball();
+        let target = Point { x: 2.0, y: 0.0 };
+        let lined_up = chip_ball(&robot_at(Point { x: -0.2, y: 0.0 }), &ball, &target, 2.0);
+        assert_eq!(lined_up.autochip_distance, Some(2.0));
+        assert_eq!(lined_up.autokick_speed, None);
+
+        let off_to_side = chip_ball(&robot_at(Point { x: 0.0, y: 1.0 }), &ball, &target, 2.0);
+        assert_eq!(off_to_side.autochip_distance, None);
+    }
+}
@@ -1,3 +1,7 @@
+pub mod constraints;
+pub mod dribble;
+pub mod intercept;
+pub mod kick;
 pub mod planner;
 pub mod tracker;
 
@@ -90,6 +94,10 @@ pub struct Trajectory {
     pub dribble: bool,
     pub autokick_speed: Option<f64>,
     pub autochip_distance: Option<f64>,
+    // Overrides the robot's default max speed when set
+    pub max_speed: Option<f64>,
+    pub max_acceleration: Option<f64>,
+    pub max_angular_speed: Option<Angle>,
 }
 
 impl Trajectory {
@@ -100,6 +108,9 @@ impl Trajectory {
             dribble: false,
             autokick_speed: None,
             autochip_distance: None,
+            max_speed: None,
+            max_acceleration: None,
+            max_angular_speed: None,
         }
     }
 }
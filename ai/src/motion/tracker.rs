@@ -1,26 +1,67 @@
+use crate::constants::GRAVITY;
 use crate::geom::{Angle, Point, Vector};
 use crate::motion::{KinematicState, Trajectory};
+use crate::proto::config::{Motion, RobotCommandMode, WheelGeometry};
 use crate::proto::ssl_simulation::robot_move_command;
-use crate::proto::ssl_simulation::{MoveLocalVelocity, RobotCommand, RobotMoveCommand};
+use crate::proto::ssl_simulation::{
+    MoveGlobalVelocity, MoveLocalVelocity, MoveWheelVelocity, RobotCommand, RobotMoveCommand,
+};
 use std::collections::vec_deque::VecDeque;
 use std::os::macos::raw::stat;
+use std::time::Instant;
+
+const DEFAULT_MAX_SPEED: f64 = 3.0;
+const DRIBBLER_SPEED_RPM: f32 = 3000.0;
+// 45 degrees gives the longest chip for a given kick speed
+const CHIP_ANGLE_DEG: f64 = 45.0;
+
+// Converts a velocity in the robot's frame into the speed each wheel must spin at
+pub fn local_velocity_to_wheel_velocity(
+    velocity: &Vector,
+    angular_velocity: &Angle,
+    geometry: &WheelGeometry,
+) -> MoveWheelVelocity {
+    let wheel_speed = |wheel_angle_deg: f64| -> f32 {
+        // Each wheel drives perpendicular to the line from the robot center to the wheel
+        let drive_direction =
+            Vector::from_angle(Angle::from_degrees(wheel_angle_deg), 1.0).perpendicular();
+        (velocity.dot(&drive_direction)
+            + angular_velocity.radians() * geometry.wheel_dist_from_center) as f32
+    };
+    MoveWheelVelocity {
+        front_right: wheel_speed(geometry.front_right_wheel_angle_deg),
+        back_right: wheel_speed(geometry.back_right_wheel_angle_deg),
+        back_left: wheel_speed(geometry.back_left_wheel_angle_deg),
+        front_left: wheel_speed(geometry.front_left_wheel_angle_deg),
+    }
+}
 
 pub struct SslSimulatorTrajectoryTracker {
     id: usize,
+    config: Motion,
     state: Option<KinematicState>,
     trajectory: Option<Trajectory>,
     tracking_points: VecDeque<Point>,
     current_tracking_point: Option<Point>,
+    // The last command sent, so acceleration can be limited when the trajectory asks for it
+    last_velocity: Vector,
+    last_run_time: Option<Instant>,
+    // So a bad command mode is only reported once
+    warned_invalid_command_mode: bool,
 }
 
 impl SslSimulatorTrajectoryTracker {
-    pub fn new(id: usize) -> SslSimulatorTrajectoryTracker {
+    pub fn new(id: usize, config: Motion) -> SslSimulatorTrajectoryTracker {
         SslSimulatorTrajectoryTracker {
             id,
+            config,
             state: None,
             trajectory: None,
             tracking_points: VecDeque::new(),
             current_tracking_point: None,
+            last_velocity: Vector::new(),
+            last_run_time: None,
+            warned_invalid_command_mode: false,
         }
     }
 
@@ -28,11 +69,8 @@ impl SslSimulatorTrajectoryTracker {
         self.trajectory = Some(trajectory);
         // The first state is always the current state so ignore it for tracking, otherwise
         // the robot stutters each time it gets a new trajectory
-        // self.tracking_points = VecDeque::from(&self.trajectory.unwrap().points[self.trajectory.unwrap().points.len()-1..].to_owned().to_vec().clone());
         self.tracking_points.clear();
-        for x in &self.trajectory.as_ref().unwrap().points
-            [self.trajectory.as_ref().unwrap().points.len() - 1..]
-        {
+        for x in self.trajectory.as_ref().unwrap().points.iter().skip(1) {
             self.tracking_points.push_back(x.clone());
         }
         if !self.tracking_points.is_empty() {
@@ -63,27 +101,90 @@ impl SslSimulatorTrajectoryTracker {
         let state = self.state.as_ref().unwrap();
         let current_tracking_point = self.current_tracking_point.as_ref().unwrap();
 
+        let trajectory = self.trajectory.as_ref().unwrap();
+        let now = Instant::now();
         let target_position = current_tracking_point;
         let position_error: Vector = target_position - &state.position;
-        let desired_velocity = if position_error.length() < 1.0e-3 {
+        let mut desired_velocity = if position_error.length() < 1.0e-3 {
             Vector::new()
         } else {
-            position_error.norm((position_error.length() * 2.5).min(3.0))
+            let max_speed = trajectory.max_speed.unwrap_or(DEFAULT_MAX_SPEED);
+            position_error.norm((position_error.length() * 2.5).min(max_speed))
         };
-        let desired_velocity = desired_velocity.rotate(&-state.orientation);
+        if let (Some(max_acceleration), Some(last_run_time)) =
+            (trajectory.max_acceleration, self.last_run_time)
+        {
+            let max_velocity_change =
+                max_acceleration * now.duration_since(last_run_time).as_secs_f64();
+            let velocity_change = desired_velocity - self.last_velocity;
+            if velocity_change.length() > max_velocity_change {
+                desired_velocity = self.last_velocity + velocity_change.norm(max_velocity_change);
+            }
+        }
+        self.last_velocity = desired_velocity;
+        self.last_run_time = Some(now);
 
-        let target_orientation = self.trajectory.as_ref().unwrap().final_orientation;
+        let target_orientation = trajectory.final_orientation;
         let orientation_error = state.orientation - target_orientation;
-        let desired_angular_velocity = (orientation_error / 4).min(Angle::full() * 4);
+        let mut desired_angular_velocity = (orientation_error / 4).min(Angle::full() * 4);
+        if let Some(max_angular_speed) = trajectory.max_angular_speed {
+            desired_angular_velocity = desired_angular_velocity
+                .min(max_angular_speed)
+                .max(-max_angular_speed);
+        }
 
         let mut robot_command: RobotCommand = RobotCommand::default();
         robot_command.id = self.id as u32;
-        let mut move_local_velocity: MoveLocalVelocity = MoveLocalVelocity::default();
-        move_local_velocity.forward = desired_velocity.x as f32;
-        move_local_velocity.left = desired_velocity.y as f32;
-        move_local_velocity.angular = desired_angular_velocity.radians() as f32;
-        // move_local_velocity.angular = 1.0;
-        let robot_move_command = robot_move_command::Command::LocalVelocity(move_local_velocity);
+        if trajectory.dribble {
+            robot_command.dribbler_speed = Some(DRIBBLER_SPEED_RPM);
+        }
+        if let Some(speed) = trajectory.autokick_speed {
+            // The simulator kicks as soon as the ball touches the kicker
+            robot_command.kick_speed = Some(speed as f32);
+            robot_command.kick_angle = Some(0.0);
+        }
+        if let Some(distance) = trajectory.autochip_distance {
+            // Ignoring air resistance, this lands the ball at the given distance
+            let chip_angle = Angle::from_degrees(CHIP_ANGLE_DEG);
+            let speed = (distance * GRAVITY / (chip_angle * 2.0).sin()).sqrt();
+            robot_command.kick_speed = Some(speed as f32);
+            robot_command.kick_angle = Some(CHIP_ANGLE_DEG as f32);
+        }
+        let robot_move_command = match RobotCommandMode::from_i32(self.config.command_mode) {
+            Some(RobotCommandMode::LocalVelocity) => {
+                let local_velocity = desired_velocity.rotate(&-state.orientation);
+                let mut move_local_velocity: MoveLocalVelocity = MoveLocalVelocity::default();
+                move_local_velocity.forward = local_velocity.x as f32;
+                move_local_velocity.left = local_velocity.y as f32;
+                move_local_velocity.angular = desired_angular_velocity.radians() as f32;
+                robot_move_command::Command::LocalVelocity(move_local_velocity)
+            }
+            Some(RobotCommandMode::GlobalVelocity) => {
+                let mut move_global_velocity: MoveGlobalVelocity = MoveGlobalVelocity::default();
+                move_global_velocity.x = desired_velocity.x as f32;
+                move_global_velocity.y = desired_velocity.y as f32;
+                move_global_velocity.angular = desired_angular_velocity.radians() as f32;
+                robot_move_command::Command::GlobalVelocity(move_global_velocity)
+            }
+            Some(RobotCommandMode::WheelVelocity) => {
+                let local_velocity = desired_velocity.rotate(&-state.orientation);
+                robot_move_command::Command::WheelVelocity(local_velocity_to_wheel_velocity(
+                    &local_velocity,
+                    &desired_angular_velocity,
+                    &self.config.wheel_geometry,
+                ))
+            }
+            None => {
+                if !self.warned_invalid_command_mode {
+                    println!(
+                        "Invalid command mode {}, not sending commands to robot {}",
+                        self.config.command_mode, self.id
+                    );
+                    self.warned_invalid_command_mode = true;
+                }
+                return None;
+            }
+        };
         let mut move_command = RobotMoveCommand::default();
         move_command.command = Some(robot_move_command);
         robot_command.move_command = Some(move_command);
@@ -91,3 +192,68 @@ impl SslSimulatorTrajectoryTracker {
         Some(robot_command)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_config;
+
+    fn symmetric_geometry() -> WheelGeometry {
+        WheelGeometry {
+            front_right_wheel_angle_deg: -45.0,
+            back_right_wheel_angle_deg: -135.0,
+            back_left_wheel_angle_deg: 135.0,
+            front_left_wheel_angle_deg: 45.0,
+            wheel_dist_from_center: 0.08,
+        }
+    }
+
+    #[test]
+    fn wheel_velocity_forward() {
+        let result = local_velocity_to_wheel_velocity(
+            &Vector { x: 1.0, y: 0.0 },
+            &Angle::zero(),
+            &symmetric_geometry(),
+        );
+        let expected = (0.5_f64).sqrt() as f32;
+        assert!((result.front_right - expected).abs() < 1.0e-6);
+        assert!((result.back_right - expected).abs() < 1.0e-6);
+        assert!((result.back_left + expected).abs() < 1.0e-6);
+        assert!((result.front_left + expected).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn invalid_command_mode_sends_nothing() {
+        let mut config = load_config().unwrap().motion;
+        config.command_mode = -1;
+        let mut tracker = SslSimulatorTrajectoryTracker::new(0, config);
+        tracker.update_most_recently_observe_state(KinematicState {
+            position: Point::new(),
+            orientation: Angle::zero(),
+            velocity: Vector::new(),
+            angular_velocity: Angle::zero(),
+        });
+        let mut trajectory = Trajectory::new();
+        trajectory.points = vec![Point::new(), Point { x: 1.0, y: 0.0 }];
+        tracker.update_trajectory(trajectory);
+        assert!(tracker.run().is_none());
+        assert!(tracker.run().is_none());
+    }
+
+    #[test]
+    fn wheel_velocity_rotate_in_place() {
+        let result = local_velocity_to_wheel_velocity(
+            &Vector::new(),
+            &Angle::from_radians(2.0),
+            &symmetric_geometry(),
+        );
+        for speed in [
+            result.front_right,
+            result.back_right,
+            result.back_left,
+            result.front_left,
+        ] {
+            assert!((speed - 0.16).abs() < 1.0e-6);
+        }
+    }
+}
@@ -1,8 +1,22 @@
 use crate::geom::Point;
-use crate::world::Ball;
+use crate::proto::config::BallModel;
+use crate::world::{Ball, BallFriction};
 use float_cmp::ApproxEqUlps;
 use std::collections::vec_deque::VecDeque;
 
+// Enough history to fit the deceleration over roughly a third of a second of vision data
+const HISTORY_SIZE: usize = 20;
+const MIN_SAMPLES_FOR_ESTIMATE: usize = 10;
+// Slower than this, the ball is basically stationary and vision noise dominates
+const MIN_ESTIMATION_SPEED: f64 = 0.3;
+// How much each new deceleration estimate moves the running estimate
+const ESTIMATE_SMOOTHING_FACTOR: f64 = 0.05;
+// Anything outside this range is a collision or noise, not friction
+const MIN_DECELERATION: f64 = 0.05;
+const MAX_DECELERATION: f64 = 10.0;
+// Speed is averaged over a few frames when detecting kicks so single noisy frames don't count
+const NUM_SPEED_SAMPLES_TO_AVERAGE: usize = 3;
+
 pub struct BallDetection {
     pub position: Point,
     pub timestamp: f64,
@@ -10,12 +24,27 @@ pub struct BallDetection {
 
 pub struct BallFilter {
     detections: VecDeque<BallDetection>,
+    config: BallModel,
+    sliding_deceleration: f64,
+    rolling_deceleration: f64,
+    // The fastest the ball has moved since it was last kicked
+    kick_speed: f64,
+    kick_timestamp: f64,
+    // Below this speed the ball is rolling. It's kept after the ball stops so slow balls
+    // are still modelled properly, and until we've seen a kick the ball is assumed to be rolling
+    rolling_speed: f64,
 }
 
 impl BallFilter {
-    pub fn new() -> BallFilter {
+    pub fn new(config: BallModel) -> BallFilter {
         BallFilter {
             detections: VecDeque::new(),
+            sliding_deceleration: config.sliding_deceleration,
+            rolling_deceleration: config.rolling_deceleration,
+            config,
+            kick_speed: 0.0,
+            kick_timestamp: f64::NEG_INFINITY,
+            rolling_speed: f64::INFINITY,
         }
     }
 
@@ -29,9 +58,89 @@ impl BallFilter {
             return;
         }
         self.detections.push_back(detection);
-        if self.detections.len() > 2 {
+        self.detections
+            .make_contiguous()
+            .sort_by(|a, b| a.timestamp.partial_cmp(&b.timestamp).unwrap());
+        if self.detections.len() > HISTORY_SIZE {
             self.detections.pop_front();
         }
+        self.update_friction_estimate();
+    }
+
+    // (timestamp, speed) between each pair of consecutive detections
+    fn speed_samples(&self) -> Vec<(f64, f64)> {
+        self.detections
+            .iter()
+            .zip(self.detections.iter().skip(1))
+            .map(|(a, b)| {
+                let time_diff = b.timestamp - a.timestamp;
+                (
+                    (a.timestamp + b.timestamp) / 2.0,
+                    (b.position - a.position).length() / time_diff,
+                )
+            })
+            .collect()
+    }
+
+    fn update_friction_estimate(&mut self) {
+        let samples = self.speed_samples();
+        if samples.len() < NUM_SPEED_SAMPLES_TO_AVERAGE {
+            return;
+        }
+        let recent_samples = &samples[samples.len() - NUM_SPEED_SAMPLES_TO_AVERAGE..];
+        let recent_speed = recent_samples.iter().map(|(_, s)| s).sum::<f64>()
+            / NUM_SPEED_SAMPLES_TO_AVERAGE as f64;
+        if recent_speed < MIN_ESTIMATION_SPEED {
+            self.kick_speed = 0.0;
+            return;
+        }
+        if recent_speed > self.kick_speed {
+            // The ball is speeding up, so it must have just been kicked
+            self.kick_speed = recent_speed;
+            self.kick_timestamp = recent_samples[NUM_SPEED_SAMPLES_TO_AVERAGE - 1].0;
+            self.rolling_speed = recent_speed * self.config.sliding_to_rolling_speed_ratio;
+            return;
+        }
+
+        if !self.config.estimate_friction_online {
+            return;
+        }
+        let samples: Vec<(f64, f64)> = samples
+            .into_iter()
+            .filter(|(t, s)| *t > self.kick_timestamp && *s > MIN_ESTIMATION_SPEED)
+            .collect();
+        if samples.len() < MIN_SAMPLES_FOR_ESTIMATE {
+            return;
+        }
+        let rolling_speed = self.rolling_speed;
+        let all_sliding = samples.iter().all(|(_, s)| *s > rolling_speed);
+        let all_rolling = samples.iter().all(|(_, s)| *s <= rolling_speed);
+        if !all_sliding && !all_rolling {
+            return;
+        }
+
+        // Least squares fit of speed over time. The slope is the acceleration
+        let n = samples.len() as f64;
+        let mean_t = samples.iter().map(|(t, _)| t).sum::<f64>() / n;
+        let mean_s = samples.iter().map(|(_, s)| s).sum::<f64>() / n;
+        let covariance: f64 = samples
+            .iter()
+            .map(|(t, s)| (t - mean_t) * (s - mean_s))
+            .sum();
+        let variance: f64 = samples.iter().map(|(t, _)| (t - mean_t).powi(2)).sum();
+        if variance < 1.0e-12 {
+            return;
+        }
+        let deceleration = -covariance / variance;
+        if !(MIN_DECELERATION..=MAX_DECELERATION).contains(&deceleration) {
+            return;
+        }
+        let estimate = if all_sliding {
+            &mut self.sliding_deceleration
+        } else {
+            &mut self.rolling_deceleration
+        };
+        *estimate += ESTIMATE_SMOOTHING_FACTOR * (deceleration - *estimate);
     }
 
     pub fn get_ball(&mut self) -> Option<Ball> {
@@ -39,13 +148,66 @@ impl BallFilter {
             return None;
         }
 
-        // let sorted: Vec<BallDetection> = self.detections.make_contiguous().iter().sorted_by(|a, b| {a.timestamp.partial_cmp(&b.timestamp).unwrap()}).collect();
-        self.detections
-            .make_contiguous()
-            .sort_by(|a, b| a.timestamp.partial_cmp(&b.timestamp).unwrap());
-        let position = self.detections[1].position;
-        let time_diff = self.detections[1].timestamp - self.detections[0].timestamp;
-        let velocity = (self.detections[1].position - self.detections[0].position) / time_diff;
-        Some(Ball { position, velocity })
+        let latest = &self.detections[self.detections.len() - 1];
+        let previous = &self.detections[self.detections.len() - 2];
+        let position = latest.position;
+        let time_diff = latest.timestamp - previous.timestamp;
+        let velocity = (latest.position - previous.position) / time_diff;
+        Some(Ball {
+            position,
+            velocity,
+            friction: BallFriction {
+                sliding_deceleration: self.sliding_deceleration,
+                rolling_deceleration: self.rolling_deceleration,
+                rolling_speed: self.rolling_speed,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter() -> BallFilter {
+        BallFilter::new(BallModel {
+            sliding_deceleration: 3.0,
+            rolling_deceleration: 0.35,
+            sliding_to_rolling_speed_ratio: 0.65,
+            estimate_friction_online: false,
+        })
+    }
+
+    fn add_constant_speed(filter: &mut BallFilter, start: f64, speed: f64, num: usize) {
+        for i in 0..num {
+            let t = start + i as f64 * 0.01;
+            filter.add_detection(BallDetection {
+                position: Point {
+                    x: speed * t,
+                    y: 0.0,
+                },
+                timestamp: t,
+            });
+        }
+    }
+
+    #[test]
+    fn slow_ball_is_rolling_before_any_kick() {
+        let mut filter = filter();
+        add_constant_speed(&mut filter, 0.0, 0.2, 10);
+        let ball = filter.get_ball().unwrap();
+        assert!(ball.friction.rolling_speed > ball.velocity.length());
+    }
+
+    #[test]
+    fn rolling_speed_is_kept_after_ball_slows_down() {
+        let mut filter = filter();
+        add_constant_speed(&mut filter, 0.0, 4.0, 10);
+        let kicked = filter.get_ball().unwrap();
+        assert!((kicked.friction.rolling_speed - 4.0 * 0.65).abs() < 0.1);
+
+        add_constant_speed(&mut filter, 1.0, 0.1, 25);
+        let slow = filter.get_ball().unwrap();
+        assert_eq!(slow.friction.rolling_speed, kicked.friction.rolling_speed);
     }
 }
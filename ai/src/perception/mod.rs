@@ -4,7 +4,7 @@ mod robot_filter;
 use crate::communication::buffer::{NodeReceiver, NodeSender};
 use crate::communication::node::Node;
 use crate::constants::{METERS_PER_MILLIMETER, MILLIMETERS_PER_METER};
-use crate::geom::{Angle, Point};
+use crate::geom::{Angle, Point, Segment};
 use crate::proto;
 use crate::proto::config;
 use crate::proto::config::Config;
@@ -12,7 +12,10 @@ use crate::proto::ssl_gamecontroller;
 use crate::proto::ssl_gamecontroller::referee;
 use crate::proto::ssl_gamecontroller::referee::Command;
 use crate::proto::ssl_vision::SslDetectionRobot;
-use crate::world::{Ball, Field, GameState, Robot, Team, TeamInfo, World};
+use crate::world::{
+    validate_field, Ball, Division, Field, FieldArc, FieldLine, GameState, Robot, Team, TeamInfo,
+    World,
+};
 use ball_filter::{BallDetection, BallFilter};
 use multiqueue2;
 use robot_filter::{RobotDetection, TeamFilter};
@@ -36,6 +39,8 @@ pub struct Perception {
     friendly_team_filter: TeamFilter,
     enemy_team_filter: TeamFilter,
     world: World,
+    // The referee repeats the current command in every packet, so we only act on new ones
+    last_referee_command_counter: Option<u32>,
     config: Arc<Mutex<Config>>,
 }
 
@@ -53,22 +58,12 @@ impl Node for Perception {
         let ssl_referee_packets = self.input.ssl_gc.dump()?;
         if !ssl_referee_packets.is_empty() {
             for packet in &ssl_referee_packets {
-                self.world.friendly_team_info = TeamInfo::from_referee(
-                    Some(packet),
+                apply_referee_packet(
+                    &mut self.world,
+                    &mut self.last_referee_command_counter,
+                    packet,
                     &self.config.lock().unwrap().perception,
-                    true,
                 );
-                self.world.enemy_team_info = TeamInfo::from_referee(
-                    Some(packet),
-                    &self.config.lock().unwrap().perception,
-                    false,
-                );
-                if let Some(info) = &self.world.friendly_team_info {
-                    self.world.game_state.update_command(
-                        referee::Command::from_i32(packet.command).unwrap(),
-                        info.is_blue,
-                    )
-                }
             }
         }
 
@@ -132,11 +127,35 @@ impl Node for Perception {
                 }
 
                 if let Some(geometry) = packet.geometry {
-                    self.world.field = Some(field_from_proto(&geometry.field));
+                    let config = self.config.lock().unwrap();
+                    let division = Division::from_config(&config.gameplay);
+                    let mut field = field_from_proto(&geometry.field, division);
+                    if let Some(info) = &self.world.friendly_team_info {
+                        field.defending_positive_side = info.defending_positive_side;
+                    }
+                    // Geometry is sent over and over, so only complain when it changes
+                    if self.world.field.as_ref() != Some(&field) {
+                        for problem in validate_field(&field, division, &config.rules) {
+                            println!("Invalid field geometry: {}", problem);
+                        }
+                    }
+                    self.world.field = Some(field);
                 }
             }
 
+            // Which side we're defending can change at half time
+            if let (Some(field), Some(info)) =
+                (&mut self.world.field, &self.world.friendly_team_info)
+            {
+                field.defending_positive_side = info.defending_positive_side;
+            }
             self.world.ball = self.ball_filter.get_ball();
+            if let Some(ball) = &self.world.ball {
+                let rules = self.config.lock().unwrap().rules.clone();
+                self.world
+                    .game_state
+                    .update_is_restart_completed(ball.position, rules);
+            }
             self.world.friendly_team = self.friendly_team_filter.get_team();
             if let Some(info) = &self.world.friendly_team_info {
                 self.world.friendly_team.set_goalie(Some(info.goalie_id));
@@ -152,10 +171,11 @@ impl Node for Perception {
     }
 
     fn new(input: Self::Input, output: Self::Output, config: Arc<Mutex<Config>>) -> Self {
+        let ball_model = config.lock().unwrap().perception.ball_model.clone();
         Self {
             input,
             output,
-            ball_filter: BallFilter::new(),
+            ball_filter: BallFilter::new(ball_model),
             friendly_team_filter: TeamFilter::new(),
             enemy_team_filter: TeamFilter::new(),
             world: World {
@@ -167,6 +187,7 @@ impl Node for Perception {
                 friendly_team_info: None,
                 enemy_team_info: None,
             },
+            last_referee_command_counter: None,
             config,
         }
     }
@@ -176,7 +197,39 @@ impl Node for Perception {
     }
 }
 
-fn field_from_proto(field_pb: &proto::ssl_vision::SslGeometryFieldSize) -> Field {
+// Updates the team info and game state from a referee packet. Commands are only applied the
+// first time we see their command counter, since every packet repeats the current command
+fn apply_referee_packet(
+    world: &mut World,
+    last_command_counter: &mut Option<u32>,
+    packet: &ssl_gamecontroller::Referee,
+    config: &config::Perception,
+) {
+    world.friendly_team_info = TeamInfo::from_referee(Some(packet), config, true);
+    world.enemy_team_info = TeamInfo::from_referee(Some(packet), config, false);
+    world
+        .game_state
+        .update_ball_placement_position(packet.designated_position.as_ref().map(|p| Point {
+            x: p.x as f64 * METERS_PER_MILLIMETER,
+            y: p.y as f64 * METERS_PER_MILLIMETER,
+        }));
+    if *last_command_counter == Some(packet.command_counter) {
+        return;
+    }
+    if let Some(info) = &world.friendly_team_info {
+        *last_command_counter = Some(packet.command_counter);
+        world.game_state.update_command(
+            referee::Command::from_i32(packet.command).unwrap(),
+            info.is_blue,
+        )
+    }
+}
+
+fn field_from_proto(
+    field_pb: &proto::ssl_vision::SslGeometryFieldSize,
+    division: Division,
+) -> Field {
+    let default_field = division.default_field();
     let line_length_from_name = |name: &str| -> Option<f64> {
         for line in &field_pb.field_lines {
             if line.name == name {
@@ -205,8 +258,7 @@ fn field_from_proto(field_pb: &proto::ssl_vision::SslGeometryFieldSize) -> Field
     } else {
         line_length_from_name("LeftFieldLeftPenaltyStretch").unwrap_or_else(|| {
             println!("Unable to find value for penalty area depth in proto");
-            // TODO: fallback to reasonable value based on division
-            1.5
+            default_field.defense_x_length
         })
     };
     let penalty_area_width = if let Some(width) = field_pb.penalty_area_width {
@@ -214,8 +266,7 @@ fn field_from_proto(field_pb: &proto::ssl_vision::SslGeometryFieldSize) -> Field
     } else {
         line_length_from_name("LeftPenaltyStretch").unwrap_or_else(|| {
             println!("Unable to find value for penalty area width in proto");
-            // TODO: fallback to reasonable value based on division
-            2.5
+            default_field.defense_y_length
         })
     };
     let center_circle_radius = if let Some(radius) = field_pb.center_circle_radius {
@@ -223,8 +274,7 @@ fn field_from_proto(field_pb: &proto::ssl_vision::SslGeometryFieldSize) -> Field
     } else {
         arc_radius_from_name("CenterCircle").unwrap_or_else(|| {
             println!("Unable to find value for center circle radius in proto");
-            // TODO: fallback to reasonable value based on division
-            0.5
+            default_field.center_circle_radius
         })
     };
 
@@ -237,5 +287,109 @@ fn field_from_proto(field_pb: &proto::ssl_vision::SslGeometryFieldSize) -> Field
         goal_y_length: field_pb.goal_width as f64 * METERS_PER_MILLIMETER,
         boundary_size: field_pb.boundary_width as f64 * METERS_PER_MILLIMETER,
         center_circle_radius: center_circle_radius,
+        penalty_mark_dist: field_pb
+            .goal_center_to_penalty_mark
+            .map_or(default_field.penalty_mark_dist, |d| {
+                d as f64 * METERS_PER_MILLIMETER
+            }),
+        defending_positive_side: false,
+        field_lines: field_pb
+            .field_lines
+            .iter()
+            .map(|l| FieldLine {
+                name: l.name.clone(),
+                segment: Segment::new(point_from_proto(&l.p1), point_from_proto(&l.p2)),
+                thickness: l.thickness as f64 * METERS_PER_MILLIMETER,
+            })
+            .collect(),
+        field_arcs: field_pb
+            .field_arcs
+            .iter()
+            .map(|a| FieldArc {
+                name: a.name.clone(),
+                center: point_from_proto(&a.center),
+                radius: a.radius as f64 * METERS_PER_MILLIMETER,
+                start_angle: Angle::from_radians(a.a1 as f64),
+                end_angle: Angle::from_radians(a.a2 as f64),
+                thickness: a.thickness as f64 * METERS_PER_MILLIMETER,
+            })
+            .collect(),
+    }
+}
+
+fn point_from_proto(p: &proto::ssl_vision::Vector2f) -> Point {
+    Point {
+        x: p.x as f64 * METERS_PER_MILLIMETER,
+        y: p.y as f64 * METERS_PER_MILLIMETER,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn perception_config() -> config::Perception {
+        config::Perception {
+            friendly_color: config::FriendlyColor::Blue as i32,
+            defending_side: config::DefendingSide::Negative as i32,
+            ..Default::default()
+        }
+    }
+
+    fn referee_packet(command: Command, command_counter: u32) -> ssl_gamecontroller::Referee {
+        ssl_gamecontroller::Referee {
+            command: command as i32,
+            command_counter,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn repeated_referee_command_is_only_applied_once() {
+        let config = perception_config();
+        let rules = config::Rules {
+            ball_in_play_after_restart_move_dist: 0.05,
+            ..Default::default()
+        };
+        let mut world = World {
+            ball: None,
+            friendly_team: Team::new(),
+            enemy_team: Team::new(),
+            field: None,
+            game_state: GameState::new(),
+            friendly_team_info: None,
+            enemy_team_info: None,
+        };
+        let mut last_command_counter = None;
+        let kickoff = referee_packet(Command::PrepareKickoffBlue, 1);
+        let normal_start = referee_packet(Command::NormalStart, 2);
+
+        apply_referee_packet(&mut world, &mut last_command_counter, &kickoff, &config);
+        world
+            .game_state
+            .update_is_restart_completed(Point { x: 0.0, y: 0.0 }, rules.clone());
+        apply_referee_packet(
+            &mut world,
+            &mut last_command_counter,
+            &normal_start,
+            &config,
+        );
+        assert!(world.game_state.our_kickoff());
+        world
+            .game_state
+            .update_is_restart_completed(Point { x: 0.5, y: 0.0 }, rules.clone());
+        assert!(world.game_state.playing());
+
+        // The game controller keeps sending NORMAL_START until the next command, which must
+        // not put us back into the kickoff
+        apply_referee_packet(
+            &mut world,
+            &mut last_command_counter,
+            &normal_start,
+            &config,
+        );
+        assert!(world.game_state.playing());
+        assert!(!world.game_state.kickoff());
+        assert_eq!(last_command_counter, Some(2));
     }
 }
@@ -24,6 +24,10 @@ pub mod metrics {
     include!(concat!(env!("OUT_DIR"), "/metrics.rs"));
 }
 
+pub mod heatmap {
+    include!(concat!(env!("OUT_DIR"), "/heatmap.rs"));
+}
+
 // pub mod ssl {
 //     include!(concat!(env!("OUT_DIR"), "/ssl.rs"));
 // }
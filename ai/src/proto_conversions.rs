@@ -1,3 +1,4 @@
+use crate::gameplay::evaluation::heatmap::Heatmap;
 use crate::motion::Trajectory;
 use crate::proto;
 use crate::proto::config;
@@ -77,3 +78,19 @@ pub fn trajectories_to_proto(
     }
     msg
 }
+
+pub fn heatmaps_to_proto(heatmaps: &Vec<Arc<Heatmap>>) -> proto::heatmap::Heatmaps {
+    let mut msg: proto::heatmap::Heatmaps = proto::heatmap::Heatmaps::default();
+    for h in heatmaps {
+        msg.heatmaps.push(proto::heatmap::Heatmap {
+            name: h.kind.to_string(),
+            x_min: h.origin.x,
+            y_min: h.origin.y,
+            spacing: h.spacing,
+            num_x: h.num_x as u32,
+            num_y: h.num_y as u32,
+            values: h.values.clone(),
+        });
+    }
+    msg
+}
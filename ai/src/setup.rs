@@ -3,6 +3,7 @@ use crate::communication::buffer::{node_connection, NodeReceiver, NodeSender};
 use crate::communication::node::{Node, SynchronousRunner, ThreadedRunner};
 use crate::config::load_config;
 use crate::gameplay;
+use crate::gameplay::evaluation::heatmap::Heatmap;
 use crate::geom::{Point, Vector};
 use crate::gui_bridge;
 use crate::math::{rect_sigmoid, sigmoid};
@@ -92,6 +93,11 @@ pub fn set_up_node_io() -> AllNodeIo {
             metrics_sender.clone(),
             "Trajectories".to_string(),
         );
+    let (heatmaps_sender, heatmaps_receiver) = node_connection::<Vec<Arc<Heatmap>>>(
+        1,
+        void_metrics_sender.clone(),
+        "Heatmaps".to_string(),
+    );
     let (sim_control_sender, sim_control_receiver) = node_connection::<SimulatorControl>(
         10,
         void_metrics_sender.clone(),
@@ -113,6 +119,7 @@ pub fn set_up_node_io() -> AllNodeIo {
         },
         gameplay_output: gameplay::Output {
             trajectories: trajectories_sender,
+            heatmaps: heatmaps_sender,
         },
         backend_input: backend::Input {
             trajectories: trajectories_receiver.add_stream().clone(),
@@ -128,6 +135,7 @@ pub fn set_up_node_io() -> AllNodeIo {
             world: world_receiver.add_stream().clone(),
             trajectories: trajectories_receiver.add_stream().clone(),
             metrics: metrics_receiver.add_stream().clone(),
+            heatmaps: heatmaps_receiver.add_stream().clone(),
         },
         gui_bridge_output: gui_bridge::Output {
             sim_control: sim_control_sender.clone(),
@@ -139,6 +147,7 @@ pub fn set_up_node_io() -> AllNodeIo {
     ssl_vision_receiver.unsubscribe();
     ssl_gc_receiver.unsubscribe();
     trajectories_receiver.unsubscribe();
+    heatmaps_receiver.unsubscribe();
     world_receiver.unsubscribe();
 
     result
@@ -0,0 +1,157 @@
+use crate::geom::{Point, Vector};
+
+// A kicked ball first slides across the carpet, which slows it down quickly. Once
+// it slows down enough it starts rolling without slipping and decelerates much slower
+#[derive(Clone, Copy, Debug)]
+pub struct BallFriction {
+    pub sliding_deceleration: f64,
+    pub rolling_deceleration: f64,
+    // The speed below which the ball is rolling rather than sliding
+    pub rolling_speed: f64,
+}
+
+#[derive(Clone)]
+pub struct Ball {
+    pub position: Point,
+    pub velocity: Vector,
+    pub friction: BallFriction,
+}
+
+impl Ball {
+    fn sliding_time(&self) -> f64 {
+        let speed = self.velocity.length();
+        if speed > self.friction.rolling_speed {
+            (speed - self.friction.rolling_speed) / self.friction.sliding_deceleration
+        } else {
+            0.0
+        }
+    }
+
+    fn speed_after_sliding(&self) -> f64 {
+        self.velocity.length().min(self.friction.rolling_speed)
+    }
+
+    pub fn stop_time(&self) -> f64 {
+        self.sliding_time() + self.speed_after_sliding() / self.friction.rolling_deceleration
+    }
+
+    // Distance travelled along the direction of the ball's velocity after t seconds
+    fn distance_at(&self, t: f64) -> f64 {
+        let speed = self.velocity.length();
+        let sliding_time = self.sliding_time();
+        if t <= sliding_time {
+            return speed * t - 0.5 * self.friction.sliding_deceleration * t.powi(2);
+        }
+        let sliding_dist = (speed + self.friction.rolling_speed) / 2.0 * sliding_time;
+        let rolling_speed = self.speed_after_sliding();
+        let rolling_time =
+            (t - sliding_time).min(rolling_speed / self.friction.rolling_deceleration);
+        sliding_dist + rolling_speed * rolling_time
+            - 0.5 * self.friction.rolling_deceleration * rolling_time.powi(2)
+    }
+
+    pub fn position_at(&self, t: f64) -> Point {
+        if self.velocity.length() < 1.0e-6 {
+            return self.position;
+        }
+        self.position + self.velocity.norm(self.distance_at(t.max(0.0)))
+    }
+
+    pub fn velocity_at(&self, t: f64) -> Vector {
+        let speed = self.velocity.length();
+        if speed < 1.0e-6 {
+            return Vector::new();
+        }
+        let sliding_time = self.sliding_time();
+        let speed_at_t = if t <= sliding_time {
+            speed - self.friction.sliding_deceleration * t
+        } else {
+            (self.speed_after_sliding() - self.friction.rolling_deceleration * (t - sliding_time))
+                .max(0.0)
+        };
+        self.velocity.norm(speed_at_t)
+    }
+
+    pub fn stop_position(&self) -> Point {
+        self.position_at(self.stop_time())
+    }
+
+    // How long until the ball passes the point. Only the distance along the ball's
+    // direction of travel is considered, so the point doesn't have to be exactly on the
+    // ball's path. Returns None if the ball is moving away or stops before reaching it
+    pub fn time_to_reach(&self, point: &Point) -> Option<f64> {
+        let speed = self.velocity.length();
+        if speed < 1.0e-6 {
+            return None;
+        }
+        let dist = (point - &self.position).dot(&self.velocity) / speed;
+        if dist < 0.0 {
+            return None;
+        }
+
+        let sliding_decel = self.friction.sliding_deceleration;
+        let sliding_dist = if speed > self.friction.rolling_speed {
+            (speed.powi(2) - self.friction.rolling_speed.powi(2)) / (2.0 * sliding_decel)
+        } else {
+            0.0
+        };
+        if dist <= sliding_dist {
+            return Some(
+                (speed - (speed.powi(2) - 2.0 * sliding_decel * dist).sqrt()) / sliding_decel,
+            );
+        }
+
+        let rolling_decel = self.friction.rolling_deceleration;
+        let rolling_speed = self.speed_after_sliding();
+        let rolling_dist = dist - sliding_dist;
+        let discriminant = rolling_speed.powi(2) - 2.0 * rolling_decel * rolling_dist;
+        if discriminant < 0.0 {
+            return None;
+        }
+        Some(self.sliding_time() + (rolling_speed - discriminant.sqrt()) / rolling_decel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kicked_ball() -> Ball {
+        Ball {
+            position: Point::new(),
+            velocity: Vector { x: 5.0, y: 0.0 },
+            friction: BallFriction {
+                sliding_deceleration: 3.0,
+                rolling_deceleration: 0.5,
+                rolling_speed: 3.0,
+            },
+        }
+    }
+
+    #[test]
+    fn stop_position_of_kicked_ball() {
+        let ball = kicked_ball();
+        // Slides from 5 to 3 m/s, then rolls from 3 m/s to a stop
+        let expected_dist = (25.0 - 9.0) / (2.0 * 3.0) + 9.0 / (2.0 * 0.5);
+        assert!((ball.stop_position().x - expected_dist).abs() < 1.0e-9);
+        assert!((ball.stop_time() - (2.0 / 3.0 + 6.0)).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn time_to_reach_matches_position_at() {
+        let ball = kicked_ball();
+        for t in [0.1, 0.5, 1.0, 3.0, 6.0] {
+            let p = ball.position_at(t);
+            let time = ball.time_to_reach(&p).unwrap();
+            assert!((time - t).abs() < 1.0e-6);
+        }
+    }
+
+    #[test]
+    fn time_to_reach_past_stop_position() {
+        let ball = kicked_ball();
+        let p = ball.stop_position() + Vector { x: 0.1, y: 0.0 };
+        assert!(ball.time_to_reach(&p).is_none());
+        assert!(ball.time_to_reach(&Point { x: -1.0, y: 0.0 }).is_none());
+    }
+}
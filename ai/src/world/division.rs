@@ -0,0 +1,142 @@
+use crate::proto::config::{Gameplay, Rules};
+use crate::world::Field;
+
+// The total field size in the rules includes this much space around the field lines
+const TOTAL_FIELD_MARGIN: f64 = 0.7;
+// How far the field we see may be from the size the rules say it should be
+// before we assume something is wrong
+const FIELD_SIZE_TOLERANCE: f64 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Division {
+    A,
+    B,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PenaltyProcedure {
+    // The kicker may dribble the ball up to the goal before shooting
+    DribbleThenShoot,
+    // The kicker has to shoot straight from the penalty mark
+    DirectShot,
+}
+
+// The parts of the rules that depend on which division we're playing in
+#[derive(Clone, Debug)]
+pub struct DivisionRules {
+    // Including the goalie
+    pub max_robots: usize,
+    pub penalty_procedure: PenaltyProcedure,
+    // The field plus the space around it
+    pub total_field_x_length: f64,
+    pub total_field_y_length: f64,
+}
+
+impl Division {
+    pub fn from_config(config: &Gameplay) -> Division {
+        if config.div_a {
+            Division::A
+        } else {
+            Division::B
+        }
+    }
+
+    pub fn rules(&self, rules: &Rules) -> DivisionRules {
+        match self {
+            Division::A => DivisionRules {
+                max_robots: 11,
+                penalty_procedure: PenaltyProcedure::DribbleThenShoot,
+                total_field_x_length: rules.div_a_total_field_x_length,
+                total_field_y_length: rules.div_a_total_field_y_length,
+            },
+            Division::B => DivisionRules {
+                max_robots: 6,
+                penalty_procedure: PenaltyProcedure::DirectShot,
+                total_field_x_length: rules.div_b_total_field_x_length,
+                total_field_y_length: rules.div_b_total_field_y_length,
+            },
+        }
+    }
+
+    // What the field looks like according to the rules, for anything vision doesn't tell us
+    pub fn default_field(&self) -> Field {
+        match self {
+            Division::A => Field::ssl_div_a(),
+            Division::B => Field::ssl_div_b(),
+        }
+    }
+}
+
+// Everything about the field that doesn't make sense for the division. Empty if the field is fine
+pub fn validate_field(field: &Field, division: Division, rules: &Rules) -> Vec<String> {
+    let division_rules = division.rules(rules);
+    let mut problems = vec![];
+    let expected_x_length = division_rules.total_field_x_length - 2.0 * TOTAL_FIELD_MARGIN;
+    let expected_y_length = division_rules.total_field_y_length - 2.0 * TOTAL_FIELD_MARGIN;
+    if (field.x_length - expected_x_length).abs() > FIELD_SIZE_TOLERANCE
+        || (field.y_length - expected_y_length).abs() > FIELD_SIZE_TOLERANCE
+    {
+        problems.push(format!(
+            "Field is {:.2}m x {:.2}m, but division {:?} should be {:.2}m x {:.2}m",
+            field.x_length, field.y_length, division, expected_x_length, expected_y_length
+        ));
+    }
+    if field.boundary_size > TOTAL_FIELD_MARGIN {
+        problems.push(format!(
+            "Field boundary {:.2}m is wider than the space the rules leave around the field",
+            field.boundary_size
+        ));
+    }
+    if field.defense_x_length <= 0.0
+        || field.defense_x_length >= field.x_length / 2.0
+        || field.defense_y_length <= 0.0
+        || field.defense_y_length >= field.y_length
+    {
+        problems.push(format!(
+            "Defense area {:.2}m x {:.2}m doesn't fit in the field",
+            field.defense_x_length, field.defense_y_length
+        ));
+    }
+    if field.goal_y_length <= 0.0 || field.goal_y_length >= field.defense_y_length {
+        problems.push(format!(
+            "Goal {:.2}m wide doesn't fit in the defense area",
+            field.goal_y_length
+        ));
+    }
+    if field.center_circle_radius <= 0.0 || field.center_circle_radius >= field.y_length / 2.0 {
+        problems.push(format!(
+            "Center circle radius {:.2}m doesn't fit in the field",
+            field.center_circle_radius
+        ));
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> Rules {
+        Rules {
+            div_a_total_field_x_length: 13.4,
+            div_a_total_field_y_length: 10.4,
+            div_b_total_field_x_length: 10.4,
+            div_b_total_field_y_length: 7.4,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn default_fields_are_valid_for_their_division() {
+        for division in [Division::A, Division::B] {
+            let field = division.default_field();
+            assert!(validate_field(&field, division, &rules()).is_empty());
+        }
+    }
+
+    #[test]
+    fn field_from_the_wrong_division_is_rejected() {
+        let field = Division::B.default_field();
+        assert_eq!(validate_field(&field, Division::A, &rules()).len(), 1);
+    }
+}
@@ -53,6 +53,7 @@ fn is_friendly_team_defending_positive_side(
 
 #[derive(Clone)]
 pub struct TeamInfo {
+    pub name: String,
     pub is_blue: bool,
     pub score: usize,
     pub goalie_id: usize,
@@ -73,6 +74,7 @@ impl TeamInfo {
         if let Some(msg) = referee {
             let info = if is_blue { &msg.blue } else { &msg.yellow };
             Some(TeamInfo {
+                name: info.name.clone(),
                 is_blue,
                 defending_positive_side,
                 score: info.score as usize,
@@ -80,6 +82,7 @@ impl TeamInfo {
             })
         } else {
             Some(TeamInfo {
+                name: String::new(),
                 is_blue,
                 defending_positive_side,
                 score: 0,
@@ -113,6 +116,8 @@ pub struct GameState {
     restart_reason: RestartReason,
     our_restart: bool,
     ball_position_at_restart: Option<Point>,
+    // Where the referee wants the ball to be placed
+    ball_placement_position: Option<Point>,
 }
 
 impl GameState {
@@ -122,6 +127,7 @@ impl GameState {
             restart_reason: RestartReason::None,
             our_restart: false,
             ball_position_at_restart: None,
+            ball_placement_position: None,
         }
     }
 
@@ -135,6 +141,7 @@ impl GameState {
                 self.play_state = PlayState::Stop;
                 self.restart_reason = RestartReason::None;
                 self.our_restart = false;
+                self.ball_position_at_restart = None;
             }
             Command::NormalStart => {
                 self.play_state = PlayState::Ready;
@@ -197,7 +204,7 @@ impl GameState {
         }
     }
 
-    fn update_is_restart_completed(&mut self, ball_position: Point, config: config::Rules) {
+    pub fn update_is_restart_completed(&mut self, ball_position: Point, config: config::Rules) {
         match self.play_state {
             PlayState::Setup => {
                 self.ball_position_at_restart = Some(ball_position.clone());
@@ -209,12 +216,20 @@ impl GameState {
                     {
                         self.set_restart_completed()
                     }
+                } else {
+                    // Free kicks skip the setup phase, so this is the first chance
+                    // to see where the ball is
+                    self.ball_position_at_restart = Some(ball_position.clone());
                 }
             }
             _ => (),
         }
     }
 
+    pub fn update_ball_placement_position(&mut self, position: Option<Point>) {
+        self.ball_placement_position = position;
+    }
+
     fn set_restart_completed(&mut self) {
         self.play_state = PlayState::Playing;
         self.restart_reason = RestartReason::None;
@@ -271,6 +286,9 @@ impl GameState {
     pub fn their_ball_placement(&self) -> bool {
         self.ball_placement() && !self.our_restart
     }
+    pub fn ball_placement_position(&self) -> Option<Point> {
+        self.ball_placement_position
+    }
     pub fn can_manipulate_ball(&self) -> bool {
         (self.play_state == PlayState::Playing)
             || (self.our_restart && self.play_state == PlayState::Ready)
@@ -279,12 +297,13 @@ impl GameState {
         (self.play_state != PlayState::Playing) && !self.our_restart
     }
     pub fn stay_on_side(&self) -> bool {
-        // Our robots must stay on our half of the field
+        // Our robots must stay on our half of the field. During our own kickoff the
+        // kicker is the only exception
         let ps = match self.play_state {
             PlayState::Setup | PlayState::Ready => true,
             _ => false,
         };
-        ps && (self.restart_reason == RestartReason::Kickoff) && !self.our_restart
+        ps && (self.restart_reason == RestartReason::Kickoff)
     }
     pub fn stay_behind_penalty_line(&self) -> bool {
         self.restart_reason == RestartReason::Penalty
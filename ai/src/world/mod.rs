@@ -1,8 +1,12 @@
+mod ball;
+mod division;
 mod game_state;
 mod vision;
 
+pub use ball::{Ball, BallFriction};
+pub use division::{validate_field, Division, DivisionRules, PenaltyProcedure};
 pub use game_state::{GameState, TeamInfo};
-pub use vision::{Ball, Field, Robot, Team};
+pub use vision::{Field, FieldArc, FieldLine, Robot, Team};
 
 #[derive(Clone)]
 pub struct World {
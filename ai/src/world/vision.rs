@@ -1,8 +1,29 @@
-use crate::geom::{Angle, Point, Rectangle, Vector};
+use crate::geom::{Angle, Circle, Point, Rectangle, Segment, Vector};
 use crate::motion::KinematicState;
 use std::collections::HashMap;
 
-#[derive(Clone)]
+// A line painted on the field, as named in the SSL geometry packet
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldLine {
+    pub name: String,
+    pub segment: Segment,
+    pub thickness: f64,
+}
+
+// An arc painted on the field, running counterclockwise from the start angle to the end angle
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldArc {
+    pub name: String,
+    pub center: Point,
+    pub radius: f64,
+    pub start_angle: Angle,
+    pub end_angle: Angle,
+    pub thickness: f64,
+}
+
+// Positions are in vision coordinates, so everything that belongs to one team or the
+// other depends on which side we're defending
+#[derive(Clone, PartialEq)]
 pub struct Field {
     pub x_length: f64,
     pub y_length: f64,
@@ -12,9 +33,31 @@ pub struct Field {
     pub goal_y_length: f64,
     pub boundary_size: f64,
     pub center_circle_radius: f64,
+    // From the center of the goal being attacked
+    pub penalty_mark_dist: f64,
+    pub defending_positive_side: bool,
+    pub field_lines: Vec<FieldLine>,
+    pub field_arcs: Vec<FieldArc>,
 }
 
 impl Field {
+    pub fn ssl_div_a() -> Field {
+        Field {
+            x_length: 12.0,
+            y_length: 9.0,
+            defense_x_length: 1.8,
+            defense_y_length: 3.6,
+            goal_x_length: 0.18,
+            goal_y_length: 1.8,
+            boundary_size: 0.3,
+            center_circle_radius: 0.5,
+            penalty_mark_dist: 8.0,
+            defending_positive_side: false,
+            field_lines: vec![],
+            field_arcs: vec![],
+        }
+    }
+
     pub fn ssl_div_b() -> Field {
         Field {
             x_length: 9.0,
@@ -25,6 +68,19 @@ impl Field {
             goal_y_length: 1.0,
             boundary_size: 0.3,
             center_circle_radius: 0.5,
+            penalty_mark_dist: 6.0,
+            defending_positive_side: false,
+            field_lines: vec![],
+            field_arcs: vec![],
+        }
+    }
+
+    // 1 if our goal is on the +x side, -1 otherwise
+    pub fn friendly_side(&self) -> f64 {
+        if self.defending_positive_side {
+            1.0
+        } else {
+            -1.0
         }
     }
 
@@ -41,31 +97,158 @@ impl Field {
         )
     }
 
-    pub fn enemy_defense_area(&self) -> Rectangle {
+    pub fn field_with_boundary(&self) -> Rectangle {
+        self.touch_lines().expand(self.boundary_size)
+    }
+
+    pub fn center_circle(&self) -> Circle {
+        Circle {
+            center: Point::new(),
+            radius: self.center_circle_radius,
+        }
+    }
+
+    pub fn halfway_line(&self) -> Segment {
+        Segment::new(
+            Point {
+                x: 0.0,
+                y: -self.y_length / 2.0,
+            },
+            Point {
+                x: 0.0,
+                y: self.y_length / 2.0,
+            },
+        )
+    }
+
+    fn goal_center(&self, side: f64) -> Point {
+        Point {
+            x: side * self.x_length / 2.0,
+            y: 0.0,
+        }
+    }
+
+    // The inside of the goal, behind the goal line
+    fn goal(&self, side: f64) -> Rectangle {
         Rectangle::new(
             Point {
-                x: self.x_length / 2.0 - self.defense_x_length,
-                y: -self.defense_y_length / 2.0,
+                x: side * self.x_length / 2.0,
+                y: -self.goal_y_length / 2.0,
             },
             Point {
-                x: self.x_length / 2.0,
-                y: self.defense_y_length / 2.0,
+                x: side * (self.x_length / 2.0 + self.goal_x_length),
+                y: self.goal_y_length / 2.0,
             },
         )
     }
 
-    pub fn friendly_defense_area(&self) -> Rectangle {
+    // Where the posts meet the goal line, with the smaller y first
+    fn goal_posts(&self, side: f64) -> (Point, Point) {
+        let x = side * self.x_length / 2.0;
+        (
+            Point {
+                x,
+                y: -self.goal_y_length / 2.0,
+            },
+            Point {
+                x,
+                y: self.goal_y_length / 2.0,
+            },
+        )
+    }
+
+    fn goal_line(&self, side: f64) -> Segment {
+        let x = side * self.x_length / 2.0;
+        Segment::new(
+            Point {
+                x,
+                y: -self.y_length / 2.0,
+            },
+            Point {
+                x,
+                y: self.y_length / 2.0,
+            },
+        )
+    }
+
+    fn defense_area(&self, side: f64) -> Rectangle {
         Rectangle::new(
             Point {
-                x: -self.x_length / 2.0,
+                x: side * self.x_length / 2.0,
                 y: -self.defense_y_length / 2.0,
             },
             Point {
-                x: -self.x_length / 2.0 + self.defense_x_length,
+                x: side * (self.x_length / 2.0 - self.defense_x_length),
                 y: self.defense_y_length / 2.0,
             },
         )
     }
+
+    // The penalty mark that's used to attack the goal on this side
+    fn penalty_mark(&self, side: f64) -> Point {
+        Point {
+            x: side * (self.x_length / 2.0 - self.penalty_mark_dist),
+            y: 0.0,
+        }
+    }
+
+    pub fn friendly_goal_center(&self) -> Point {
+        self.goal_center(self.friendly_side())
+    }
+
+    pub fn enemy_goal_center(&self) -> Point {
+        self.goal_center(-self.friendly_side())
+    }
+
+    pub fn friendly_goal(&self) -> Rectangle {
+        self.goal(self.friendly_side())
+    }
+
+    pub fn enemy_goal(&self) -> Rectangle {
+        self.goal(-self.friendly_side())
+    }
+
+    pub fn friendly_goal_posts(&self) -> (Point, Point) {
+        self.goal_posts(self.friendly_side())
+    }
+
+    pub fn enemy_goal_posts(&self) -> (Point, Point) {
+        self.goal_posts(-self.friendly_side())
+    }
+
+    pub fn friendly_goal_line(&self) -> Segment {
+        self.goal_line(self.friendly_side())
+    }
+
+    pub fn enemy_goal_line(&self) -> Segment {
+        self.goal_line(-self.friendly_side())
+    }
+
+    pub fn enemy_defense_area(&self) -> Rectangle {
+        self.defense_area(-self.friendly_side())
+    }
+
+    pub fn friendly_defense_area(&self) -> Rectangle {
+        self.defense_area(self.friendly_side())
+    }
+
+    // Where the ball goes when the enemy takes a penalty on our goal
+    pub fn friendly_penalty_mark(&self) -> Point {
+        self.penalty_mark(self.friendly_side())
+    }
+
+    // Where the ball goes when we take a penalty
+    pub fn enemy_penalty_mark(&self) -> Point {
+        self.penalty_mark(-self.friendly_side())
+    }
+
+    pub fn line(&self, name: &str) -> Option<&FieldLine> {
+        self.field_lines.iter().find(|l| l.name == name)
+    }
+
+    pub fn arc(&self, name: &str) -> Option<&FieldArc> {
+        self.field_arcs.iter().find(|a| a.name == name)
+    }
 }
 
 #[derive(Clone)]
@@ -74,12 +257,6 @@ pub struct Robot {
     pub state: KinematicState,
 }
 
-#[derive(Clone)]
-pub struct Ball {
-    pub position: Point,
-    pub velocity: Vector,
-}
-
 #[derive(Clone)]
 pub struct Team {
     goalie_id: Option<usize>,
@@ -148,3 +325,32 @@ impl Team {
         self.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geometry_flips_with_defending_side() {
+        let mut field = Field::ssl_div_b();
+        assert_eq!(field.friendly_goal_center(), Point { x: -4.5, y: 0.0 });
+        assert!(field.friendly_goal().contains(&Point { x: -4.6, y: 0.0 }));
+        assert!(field
+            .friendly_defense_area()
+            .contains(&Point { x: -4.0, y: 0.0 }));
+        assert_eq!(field.enemy_penalty_mark(), Point { x: -1.5, y: 0.0 });
+
+        field.defending_positive_side = true;
+        assert_eq!(field.friendly_goal_center(), Point { x: 4.5, y: 0.0 });
+        assert_eq!(field.enemy_goal_posts().0, Point { x: -4.5, y: -0.5 });
+        assert!(field.friendly_goal().contains(&Point { x: 4.6, y: 0.0 }));
+        assert!(field
+            .friendly_defense_area()
+            .contains(&Point { x: 4.0, y: 0.0 }));
+        assert!(field
+            .enemy_defense_area()
+            .contains(&Point { x: -4.0, y: 0.0 }));
+        assert_eq!(field.enemy_penalty_mark(), Point { x: 1.5, y: 0.0 });
+        assert_eq!(field.friendly_goal_line().start.x, 4.5);
+    }
+}
@@ -0,0 +1,50 @@
+import pyqtgraph as pg
+from PyQt6.QtCore import QRectF
+from pyqtgraph.Qt import QtGui
+from .field_layer import FieldLayer
+from threading import Lock
+from proto.heatmap_pb2 import Heatmaps
+
+# Opacity of the heatmap, so robots and the ball can still be seen underneath
+HEATMAP_ALPHA = 120
+
+
+class HeatmapLayer(FieldLayer):
+    def __init__(self, heatmap_name):
+        """Draws one of the heatmaps published by gameplay
+        :param heatmap_name: The name of the heatmap to draw
+        """
+        FieldLayer.__init__(self)
+        self.heatmap_name = heatmap_name
+        self.heatmap = None
+        self.colormap = pg.colormap.get("CET-D1")
+        self.lock = Lock()
+
+    def update_heatmaps(self, heatmaps: Heatmaps):
+        with self.lock:
+            for heatmap in heatmaps.heatmaps:
+                if heatmap.name == self.heatmap_name:
+                    self.heatmap = heatmap
+
+    def draw_heatmap(self, painter, heatmap):
+        painter.setPen(pg.mkPen(None))
+        # Values are between 0 and 1, so they can be used for the colormap directly
+        colors = self.colormap.map(list(heatmap.values), mode="qcolor")
+        for k, color in enumerate(colors):
+            i = k % heatmap.num_x
+            j = k // heatmap.num_x
+            x = heatmap.x_min + (i - 0.5) * heatmap.spacing
+            y = heatmap.y_min + (j - 0.5) * heatmap.spacing
+            color.setAlpha(HEATMAP_ALPHA)
+            painter.setBrush(QtGui.QBrush(color))
+            painter.drawRect(QRectF(x, y, heatmap.spacing, heatmap.spacing))
+
+    def paint(self, painter, option, widget):
+        """Paint this layer
+        :param painter: The painter object to draw with
+        :param option: Style information (unused)
+        :param widget: The widget that we are painting on
+        """
+        with self.lock:
+            if self.heatmap:
+                self.draw_heatmap(painter, self.heatmap)
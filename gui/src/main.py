@@ -23,10 +23,12 @@ from src.util.zmq_pub_sub import ZmqPubSub
 from util.zmq_pub_sub import ZmqPubSub
 from field.raw_vision_layer import RawVisionLayer
 from field.trajectory_layer import TrajectoryLayer
+from field.heatmap_layer import HeatmapLayer
 
 from field.filtered_vision_layer import FilteredVisionLayer
 
 from third_party.ssl_vision.messages_robocup_ssl_wrapper_pb2 import SSL_WrapperPackets
+from proto.heatmap_pb2 import Heatmaps
 from proto.metrics_pb2 import NodePerformance
 from proto.trajectory_pb2 import Trajectories
 from proto.world_pb2 import World
@@ -143,6 +145,16 @@ class RustwareGui(QMainWindow):
         )
         field.add_layer("Trajectories", trajectory_layer)
 
+        # These match the heatmap names used by gameplay
+        for name in ["PassReceive", "ShotOpenness", "EnemyPressure"]:
+            heatmap_layer = HeatmapLayer(name)
+            self.pub_sub_manager.register_callback(
+                callback=heatmap_layer.update_heatmaps,
+                topic=self.config.gui_bridge.heatmaps_topic,
+                msg_type=Heatmaps,
+            )
+            field.add_layer("{} Heatmap".format(name), heatmap_layer, visible=False)
+
         sim_control_layer = SimControlLayer(
             pub_sim_command=lambda x: self.pub_sub_manager.pub(
                 msg=x,